@@ -0,0 +1,141 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use ::actix::{fut::ActorFuture, Actor};
+
+use crate::{Tracked, WaitGroup, WaitGroupGuard};
+
+/// Tracks in-flight messages and futures of Actix actors, so that shutdown can wait for the
+/// handlers to complete with [ActorTracker::stopped()].
+///
+/// Cloned trackers share the same count, one tracker can be shared by several actors.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use actix::prelude::*;
+/// use atomic_waitgroup::actor::ActorTracker;
+///
+/// struct Worker(ActorTracker);
+///
+/// impl Actor for Worker {
+///     type Context = Context<Self>;
+/// }
+///
+/// struct Work;
+///
+/// impl Message for Work {
+///     type Result = ();
+/// }
+///
+/// impl Handler<Work> for Worker {
+///     type Result = ResponseActFuture<Self, ()>;
+///
+///     fn handle(&mut self, _msg: Work, _ctx: &mut Self::Context) -> Self::Result {
+///         Box::pin(self.0.track_act(actix::fut::wrap_future(async move {
+///             // Do something
+///         })))
+///     }
+/// }
+///
+/// System::new().block_on(async move {
+///     let tracker = ActorTracker::new();
+///     let addr = Worker(tracker.clone()).start();
+///     addr.do_send(Work);
+///     addr.do_send(Work);
+///     tokio::task::yield_now().await;
+///     tracker.stopped().await;
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct ActorTracker {
+    wg: WaitGroup,
+}
+
+impl ActorTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Share the count with an existing WaitGroup
+    pub fn with_wait_group(wg: WaitGroup) -> Self {
+        Self { wg }
+    }
+
+    /// The underlying WaitGroup
+    #[inline]
+    pub fn wait_group(&self) -> &WaitGroup {
+        &self.wg
+    }
+
+    /// Return a guard for a message handled synchronously, or work spawned by a handler.
+    #[inline]
+    pub fn guard(&self) -> WaitGroupGuard {
+        self.wg.add_guard()
+    }
+
+    /// Track an ActorFuture, returned by a handler as ResponseActFuture, or spawned on the
+    /// actor context.
+    #[inline]
+    pub fn track_act<F>(&self, future: F) -> TrackedActorFuture<F> {
+        TrackedActorFuture {
+            future,
+            guard: Some(self.wg.add_guard()),
+        }
+    }
+
+    /// Track a plain future, returned by a handler as ResponseFuture.
+    #[inline]
+    pub fn track<F: Future>(&self, future: F) -> Tracked<F, WaitGroupGuard> {
+        self.wg.track(future)
+    }
+
+    /// Count of tracked messages and futures not completed.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.wg.left()
+    }
+
+    /// Wait until all the tracked messages and futures complete.
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    pub async fn stopped(&self) {
+        self.wg.wait().await;
+    }
+}
+
+/// An ActorFuture holding a guard until completion, created by [ActorTracker::track_act()].
+pub struct TrackedActorFuture<F> {
+    future: F,
+    guard: Option<WaitGroupGuard>,
+}
+
+impl<A: Actor, F: ActorFuture<A>> ActorFuture<A> for TrackedActorFuture<F> {
+    type Output = F::Output;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        srv: &mut A,
+        ctx: &mut A::Context,
+        task: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        // Safety: the future is structurally pinned and never moved, the guard is not pinned
+        let _self = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut _self.future) };
+        match future.poll(srv, ctx, task) {
+            Poll::Ready(r) => {
+                _self.guard.take();
+                Poll::Ready(r)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    ffi::c_void,
+    sync::Arc,
+    task::{Wake, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::WaitGroupGuard;
+
+/// A completion token created by [crate::WaitGroup::wrap_fn()], which calls done() once
+/// when invoked, or when dropped without being invoked.
+///
+/// For callback-based API across FFI boundary, convert it with into_raw() and pass
+/// [DoneFn::callback] along with the pointer as the completion callback.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{DoneFn, WaitGroup};
+///
+/// let wg = WaitGroup::new();
+/// let token = wg.wrap_fn();
+/// assert_eq!(wg.left(), 1);
+/// let ptr = token.into_raw();
+/// // Invoked by the foreign library on completion
+/// unsafe { DoneFn::callback(ptr) };
+/// assert_eq!(wg.left(), 0);
+/// ```
+#[must_use = "dropping the token calls done() immediately"]
+pub struct DoneFn {
+    _guard: WaitGroupGuard,
+}
+
+impl DoneFn {
+    #[inline]
+    pub(crate) fn new(guard: WaitGroupGuard) -> Self {
+        Self { _guard: guard }
+    }
+
+    /// Call done() on the WaitGroup, consuming the token.
+    #[inline]
+    pub fn call(self) {
+        drop(self);
+    }
+
+    /// Convert into a boxed closure, for API taking `FnOnce()` completion callbacks.
+    #[inline]
+    pub fn into_fn(self) -> Box<dyn FnOnce() + Send + 'static> {
+        Box::new(move || self.call())
+    }
+
+    /// Leak the token as a raw pointer, to be released by [DoneFn::callback] or
+    /// [DoneFn::from_raw].
+    #[inline]
+    pub fn into_raw(self) -> *mut c_void {
+        Box::into_raw(Box::new(self)) as *mut c_void
+    }
+
+    /// Reassemble a token from into_raw().
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from into_raw(), and not be used again afterwards.
+    #[inline]
+    pub unsafe fn from_raw(ptr: *mut c_void) -> Self {
+        *Box::from_raw(ptr as *mut DoneFn)
+    }
+
+    /// C callback calling done(), to be paired with a pointer from into_raw().
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from into_raw(), and the callback must be called only once.
+    pub unsafe extern "C" fn callback(ptr: *mut c_void) {
+        Self::from_raw(ptr).call();
+    }
+}
+
+/// Hold the guard, until the first wake or until all the waker clones are dropped.
+struct DoneWake(Mutex<Option<WaitGroupGuard>>);
+
+impl Wake for DoneWake {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    #[inline]
+    fn wake_by_ref(self: &Arc<Self>) {
+        let guard = self.0.lock().take();
+        drop(guard);
+    }
+}
+
+#[inline]
+pub(crate) fn done_waker(guard: WaitGroupGuard) -> Waker {
+    Waker::from(Arc::new(DoneWake(Mutex::new(Some(guard)))))
+}
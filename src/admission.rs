@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::{error::CountError, storage::RawWaitGroup, WaitGroupInner};
+
+const WAITING: u8 = 0;
+const GRANTED: u8 = 1;
+/// Closed by claim_at()
+const REJECTED: u8 = 2;
+
+/// Tasks blocked in acquire(), granted capacity in arrival order.
+///
+/// The head of the queue is admitted by done() on its behalf, the others wait behind it even
+/// if they would fit, so that a large or slow acquirer is never starved by later ones.
+pub(crate) struct AdmissionQueue {
+    /// Count of queued acquirers, to skip the lock in done()
+    count: AtomicUsize,
+    waiters: Mutex<VecDeque<Arc<Acquirer>>>,
+}
+
+struct Acquirer {
+    weight: usize,
+    /// The count must be at or below it before the add
+    from: u64,
+    /// WAITING, GRANTED or REJECTED, changed under the lock of the queue
+    state: AtomicU8,
+    waker: Mutex<Waker>,
+}
+
+impl AdmissionQueue {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &AdmissionQueue {
+        inner.admission.get_or_init(|| AdmissionQueue {
+            count: AtomicUsize::new(0),
+            waiters: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Admit from the head of the queue while the count allows it
+    pub(crate) fn grant(&self, inner: &WaitGroupInner) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let mut wakers = Vec::new();
+        let mut waiters = self.waiters.lock();
+        while let Some(head) = waiters.front() {
+            let state = match inner.increase_from_queued(head.weight, head.from) {
+                Ok(true) => GRANTED,
+                Ok(false) => break,
+                Err(CountError::Closed { .. }) => REJECTED,
+                Err(err) => {
+                    inner.report(err);
+                    REJECTED
+                }
+            };
+            head.state.store(state, Ordering::SeqCst);
+            wakers.push(head.waker.lock().clone());
+            waiters.pop_front();
+        }
+        self.count.store(waiters.len(), Ordering::SeqCst);
+        drop(waiters);
+        inner.wake_all(wakers);
+    }
+}
+
+/// Add `weight` to the WaitGroup once the count is at or below `limit - weight`.
+/// Return false if closed by claim_at().
+pub(crate) struct AdmissionFuture<'a> {
+    inner: &'a WaitGroupInner,
+    weight: usize,
+    limit: usize,
+    acquirer: Option<Arc<Acquirer>>,
+}
+
+impl<'a> AdmissionFuture<'a> {
+    #[inline]
+    pub(crate) fn new(inner: &'a WaitGroupInner, weight: usize, limit: usize) -> Self {
+        Self {
+            inner,
+            weight,
+            limit,
+            acquirer: None,
+        }
+    }
+
+    /// A weight above the limit is admitted alone, at count zero
+    #[inline(always)]
+    fn from(&self) -> u64 {
+        self.limit.saturating_sub(self.weight) as u64
+    }
+}
+
+impl Future for AdmissionFuture<'_> {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        let queue = AdmissionQueue::get(_self.inner);
+        let Some(acquirer) = _self.acquirer.as_ref() else {
+            // Barging only when no one is queued, in order not to overtake
+            if queue.count.load(Ordering::SeqCst) == 0 {
+                match _self.inner.increase_from(_self.weight, _self.from()) {
+                    Ok(true) => return Poll::Ready(true),
+                    Ok(false) => {}
+                    Err(CountError::Closed { .. }) => return Poll::Ready(false),
+                    Err(err) => {
+                        _self.inner.report(err);
+                        return Poll::Ready(false);
+                    }
+                }
+            }
+            let acquirer = Arc::new(Acquirer {
+                weight: _self.weight,
+                from: _self.from(),
+                state: AtomicU8::new(WAITING),
+                waker: Mutex::new(ctx.waker().clone()),
+            });
+            {
+                let mut waiters = queue.waiters.lock();
+                waiters.push_back(acquirer.clone());
+                queue.count.store(waiters.len(), Ordering::SeqCst);
+            }
+            _self.acquirer = Some(acquirer);
+            // done() might happen before queued
+            queue.grant(_self.inner);
+            return _self.check();
+        };
+        if acquirer.state.load(Ordering::SeqCst) == WAITING {
+            acquirer.waker.lock().clone_from(ctx.waker());
+        }
+        _self.check()
+    }
+}
+
+impl AdmissionFuture<'_> {
+    #[inline]
+    fn check(&mut self) -> Poll<bool> {
+        let state = self.acquirer.as_ref().unwrap().state.load(Ordering::SeqCst);
+        if state == WAITING {
+            return Poll::Pending;
+        }
+        self.acquirer = None;
+        Poll::Ready(state == GRANTED)
+    }
+}
+
+impl Drop for AdmissionFuture<'_> {
+    fn drop(&mut self) {
+        let Some(acquirer) = self.acquirer.take() else {
+            return;
+        };
+        let queue = AdmissionQueue::get(self.inner);
+        let state = {
+            let mut waiters = queue.waiters.lock();
+            let state = acquirer.state.load(Ordering::SeqCst);
+            if state == WAITING {
+                waiters.retain(|waiter| !Arc::ptr_eq(waiter, &acquirer));
+                queue.count.store(waiters.len(), Ordering::SeqCst);
+            }
+            state
+        };
+        match state {
+            // The next one might fit now
+            WAITING => queue.grant(self.inner),
+            // Admitted but never handed out
+            GRANTED => {
+                self.inner.done(self.weight);
+            }
+            _ => {}
+        }
+    }
+}
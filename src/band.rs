@@ -0,0 +1,181 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::WaitGroupInner;
+
+struct BandWaiter {
+    id: u64,
+    floor: u64,
+    ceil: u64,
+    waker: Waker,
+}
+
+/// Tasks waiting for the count to leave a band, woken by add() and done()
+pub(crate) struct BandWaiters {
+    next_id: AtomicU64,
+    /// Highest floor waited for, 0 if none, to skip the lock
+    max_floor: AtomicU64,
+    /// Lowest ceil waited for, u64::MAX if none, to skip the lock
+    min_ceil: AtomicU64,
+    /// Bumped by notify_all()
+    epoch: AtomicU64,
+    waiters: Mutex<Vec<BandWaiter>>,
+}
+
+impl BandWaiters {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &BandWaiters {
+        inner.bands.get_or_init(|| BandWaiters {
+            next_id: AtomicU64::new(1),
+            max_floor: AtomicU64::new(0),
+            min_ceil: AtomicU64::new(u64::MAX),
+            epoch: AtomicU64::new(0),
+            waiters: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Wake every waiter to re-check, by notify_waiters()
+    pub(crate) fn notify_all(&self, inner: &WaitGroupInner) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        let wakers: Vec<Waker> = {
+            let waiters = self.waiters.lock();
+            waiters.iter().map(|waiter| waiter.waker.clone()).collect()
+        };
+        inner.wake_all(wakers);
+    }
+
+    /// Called after the count changed to `left`
+    pub(crate) fn notify(&self, inner: &WaitGroupInner, left: u64) {
+        if left >= self.max_floor.load(Ordering::SeqCst)
+            && left < self.min_ceil.load(Ordering::SeqCst)
+        {
+            return;
+        }
+        let mut wakers = Vec::new();
+        {
+            let mut waiters = self.waiters.lock();
+            let mut i = 0;
+            while i < waiters.len() {
+                if left < waiters[i].floor || left >= waiters[i].ceil {
+                    wakers.push(waiters.swap_remove(i).waker);
+                } else {
+                    i += 1;
+                }
+            }
+            self.update_bounds(&waiters);
+        }
+        // Out of the lock, the woken task may poll again on this thread
+        inner.wake_all(wakers);
+    }
+
+    #[inline]
+    fn update_bounds(&self, waiters: &[BandWaiter]) {
+        let max_floor = waiters.iter().map(|w| w.floor).max().unwrap_or(0);
+        let min_ceil = waiters.iter().map(|w| w.ceil).min().unwrap_or(u64::MAX);
+        self.max_floor.store(max_floor, Ordering::SeqCst);
+        self.min_ceil.store(min_ceil, Ordering::SeqCst);
+    }
+}
+
+/// Wait until the count is below `floor` or at or above `ceil`, return the count observed.
+pub(crate) struct BandFuture<'a> {
+    inner: &'a WaitGroupInner,
+    floor: u64,
+    ceil: u64,
+    id: u64,
+    /// The epoch of notify_all() to resolve on a change of, if interruptible
+    epoch: Option<u64>,
+}
+
+impl<'a> BandFuture<'a> {
+    #[inline]
+    pub(crate) fn new(inner: &'a WaitGroupInner, floor: u64, ceil: u64) -> Self {
+        Self {
+            inner,
+            floor,
+            ceil,
+            id: 0,
+            epoch: None,
+        }
+    }
+
+    /// Also resolve on notify_waiters(), with the count in the band
+    #[inline]
+    pub(crate) fn interruptible(mut self) -> Self {
+        let epoch = BandWaiters::get(self.inner).epoch.load(Ordering::SeqCst);
+        self.epoch = Some(epoch);
+        self
+    }
+
+    #[inline]
+    pub(crate) fn check(&self) -> Option<u64> {
+        let left = self.inner.left.load(Ordering::SeqCst);
+        let notified = self.epoch.is_some_and(|epoch| {
+            self.inner
+                .bands
+                .get()
+                .is_some_and(|bands| bands.epoch.load(Ordering::SeqCst) != epoch)
+        });
+        (left < self.floor || left >= self.ceil || notified).then_some(left)
+    }
+
+    fn _clear(&mut self) {
+        if self.id == 0 {
+            return;
+        }
+        let id = self.id;
+        self.id = 0;
+        if let Some(bands) = self.inner.bands.get() {
+            let mut waiters = bands.waiters.lock();
+            waiters.retain(|waiter| waiter.id != id);
+            bands.update_bounds(&waiters);
+        }
+    }
+}
+
+impl Drop for BandFuture<'_> {
+    fn drop(&mut self) {
+        self._clear();
+    }
+}
+
+impl Future for BandFuture<'_> {
+    type Output = u64;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        if let Some(left) = _self.check() {
+            _self._clear();
+            return Poll::Ready(left);
+        }
+        let bands = BandWaiters::get(_self.inner);
+        {
+            let mut waiters = bands.waiters.lock();
+            if _self.id == 0 {
+                _self.id = bands.next_id.fetch_add(1, Ordering::Relaxed);
+            }
+            // notify() removes the woken, push again on every poll
+            match waiters.iter_mut().find(|waiter| waiter.id == _self.id) {
+                Some(waiter) => waiter.waker.clone_from(ctx.waker()),
+                None => waiters.push(BandWaiter {
+                    id: _self.id,
+                    floor: _self.floor,
+                    ceil: _self.ceil,
+                    waker: ctx.waker().clone(),
+                }),
+            }
+            bands.update_bounds(&waiters);
+        }
+        // The count changes before notify(), checking after the waker is pushed never misses it
+        if let Some(left) = _self.check() {
+            _self._clear();
+            return Poll::Ready(left);
+        }
+        Poll::Pending
+    }
+}
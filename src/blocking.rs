@@ -0,0 +1,223 @@
+// Replaced by WasmWaiters on wasm32 with the `wasm-atomics` feature
+#![cfg_attr(
+    all(target_arch = "wasm32", feature = "wasm-atomics"),
+    allow(dead_code)
+)]
+
+use std::{
+    panic::Location,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+#[cfg(feature = "tokio")]
+use log::warn;
+#[cfg(not(feature = "parking"))]
+use parking_lot::Condvar;
+use parking_lot::Mutex;
+
+use crate::{error::Elapsed, listener::Listener, WaitGroupInner};
+
+/// Threads blocked in wait_to_blocking(), any number of them.
+///
+/// By default woken by broadcast on a Condvar. With the `parking` feature, each thread has its
+/// own Parker, and only the threads whose target is reached are woken.
+pub(crate) struct BlockingWaiters {
+    /// Count of blocked threads, to skip the lock in done()
+    count: AtomicUsize,
+    /// Targets of blocked threads
+    #[cfg(not(feature = "parking"))]
+    targets: Mutex<Vec<u64>>,
+    #[cfg(not(feature = "parking"))]
+    cond: Condvar,
+    /// Targets of blocked threads, with their id
+    #[cfg(feature = "parking")]
+    targets: Mutex<Vec<(u64, usize, parking::Unparker)>>,
+    #[cfg(feature = "parking")]
+    next_id: AtomicUsize,
+}
+
+impl Listener for BlockingWaiters {
+    #[inline]
+    fn on_done(&self, _old: u64, left: u64) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let targets = self.targets.lock();
+        #[cfg(not(feature = "parking"))]
+        if targets.iter().any(|target| left <= *target) {
+            self.cond.notify_all();
+        }
+        #[cfg(feature = "parking")]
+        for (target, _, unparker) in targets.iter() {
+            if left <= *target {
+                unparker.unpark();
+            }
+        }
+    }
+}
+
+impl BlockingWaiters {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &BlockingWaiters {
+        inner.blocking.get_or_init(|| {
+            let waiters = Arc::new(BlockingWaiters {
+                count: AtomicUsize::new(0),
+                targets: Mutex::new(Vec::new()),
+                #[cfg(not(feature = "parking"))]
+                cond: Condvar::new(),
+                #[cfg(feature = "parking")]
+                next_id: AtomicUsize::new(0),
+            });
+            // Registered for the lifetime of the WaitGroup
+            inner.listeners.write().push(waiters.clone());
+            inner.has_listeners.store(true, Ordering::SeqCst);
+            waiters
+        })
+    }
+
+    /// Count of blocked threads
+    #[cfg(any(feature = "health", feature = "monitor"))]
+    #[inline]
+    pub(crate) fn blocked(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Wake every blocked thread to re-check its target
+    pub(crate) fn notify_all(&self) {
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let targets = self.targets.lock();
+        #[cfg(not(feature = "parking"))]
+        self.cond.notify_all();
+        #[cfg(feature = "parking")]
+        for (_, _, unparker) in targets.iter() {
+            unparker.unpark();
+        }
+        drop(targets);
+    }
+
+    /// Return Ok(true) if blocked
+    #[cfg(not(feature = "parking"))]
+    pub(crate) fn wait(
+        &self,
+        inner: &WaitGroupInner,
+        target: u64,
+        deadline: Option<Instant>,
+    ) -> Result<bool, Elapsed> {
+        if inner.released(target) {
+            return Ok(false);
+        }
+        let mut targets = self.targets.lock();
+        targets.push(target);
+        self.count.fetch_add(1, Ordering::SeqCst);
+        // done() notifies under the lock, checking after count is increased never misses it
+        let mut r = Ok(true);
+        while !inner.released(target) {
+            match deadline {
+                Some(deadline) => {
+                    if self.cond.wait_until(&mut targets, deadline).timed_out()
+                        && !inner.released(target)
+                    {
+                        r = Err(Elapsed);
+                        break;
+                    }
+                }
+                None => self.cond.wait(&mut targets),
+            }
+        }
+        self.count.fetch_sub(1, Ordering::SeqCst);
+        if let Some(i) = targets.iter().position(|t| *t == target) {
+            targets.swap_remove(i);
+        }
+        r
+    }
+
+    /// Return Ok(true) if blocked
+    #[cfg(feature = "parking")]
+    pub(crate) fn wait(
+        &self,
+        inner: &WaitGroupInner,
+        target: u64,
+        deadline: Option<Instant>,
+    ) -> Result<bool, Elapsed> {
+        if inner.released(target) {
+            return Ok(false);
+        }
+        let (parker, unparker) = parking::pair();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.targets.lock().push((target, id, unparker));
+        self.count.fetch_add(1, Ordering::SeqCst);
+        // done() checks count after decreasing, an unpark before park() is not lost
+        let mut r = Ok(true);
+        while !inner.released(target) {
+            match deadline {
+                Some(deadline) => {
+                    if !parker.park_deadline(deadline) && !inner.released(target) {
+                        r = Err(Elapsed);
+                        break;
+                    }
+                }
+                None => parker.park(),
+            }
+        }
+        self.count.fetch_sub(1, Ordering::SeqCst);
+        let mut targets = self.targets.lock();
+        if let Some(i) = targets.iter().position(|(_, _id, _)| *_id == id) {
+            targets.swap_remove(i);
+        }
+        r
+    }
+}
+
+/// Apply the RuntimeBlockingPolicy around the blocking `f`
+#[cfg(feature = "tokio")]
+pub(crate) fn in_runtime<R>(
+    inner: &WaitGroupInner,
+    caller: &'static Location<'static>,
+    f: impl FnOnce() -> R,
+) -> R {
+    use crate::builder::RuntimeBlockingPolicy;
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    let policy = inner.config.runtime_blocking;
+    if policy == RuntimeBlockingPolicy::Allow {
+        return f();
+    }
+    let Ok(handle) = Handle::try_current() else {
+        return f();
+    };
+    match policy {
+        RuntimeBlockingPolicy::Panic => {
+            panic!(
+                "WaitGroup blocking wait inside a tokio runtime at {}",
+                caller
+            )
+        }
+        RuntimeBlockingPolicy::BlockInPlace
+            if handle.runtime_flavor() == RuntimeFlavor::MultiThread =>
+        {
+            tokio::task::block_in_place(f)
+        }
+        _ => {
+            warn!(
+                "WaitGroup blocking wait inside a tokio runtime at {}",
+                caller
+            );
+            f()
+        }
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+#[inline(always)]
+pub(crate) fn in_runtime<R>(
+    _inner: &WaitGroupInner,
+    _caller: &'static Location<'static>,
+    f: impl FnOnce() -> R,
+) -> R {
+    f()
+}
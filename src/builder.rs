@@ -0,0 +1,154 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    error::CountError,
+    inner::WaitGroupInner,
+    observer::{ObserverSlot, WaitGroupObserver},
+    GenericWaitGroup, WaitGroup,
+};
+
+/// What to do when the last WaitGroup handle is dropped while count is left.
+///
+/// Guards are not handles, they keep counting down after the WaitGroup is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Do nothing (default)
+    #[default]
+    Ignore,
+    /// Log a warning with the count left
+    Warn,
+    /// Panic in debug builds, log an error in release builds
+    DebugPanic,
+}
+
+/// How a WaitGroup reports a [CountError].
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// Log an error and panic (default), except [CountError::WakerPanicked] which is only
+    /// logged
+    #[default]
+    Panic,
+    /// Log an error
+    Log,
+    /// Call the closure
+    Callback(Arc<dyn Fn(&CountError) + Send + Sync>),
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panic => write!(f, "Panic"),
+            Self::Log => write!(f, "Log"),
+            Self::Callback(_) => write!(f, "Callback(..)"),
+        }
+    }
+}
+
+/// What wait_blocking() does when called inside a tokio runtime, where it stalls a worker
+/// thread, or deadlocks the runtime if the guards are dropped by its tasks.
+///
+/// Detected by Handle::try_current(), which also succeeds on the threads of spawn_blocking().
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuntimeBlockingPolicy {
+    /// Block as usual (default)
+    #[default]
+    Allow,
+    /// Log a warning and block
+    Warn,
+    /// Panic
+    Panic,
+    /// Block inside tokio::task::block_in_place(), so that the tasks of the worker are moved
+    /// to other threads. On a current_thread runtime, where it is not possible, log a warning
+    /// and block.
+    BlockInPlace,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Config {
+    pub(crate) drop_policy: DropPolicy,
+    pub(crate) poison_on_panic: bool,
+    pub(crate) observer: ObserverSlot,
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) max_count: Option<usize>,
+    pub(crate) go_semantics: bool,
+    #[cfg(feature = "tokio")]
+    pub(crate) runtime_blocking: RuntimeBlockingPolicy,
+}
+
+/// Builder for a WaitGroup with non-default options.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{DropPolicy, WaitGroup};
+///
+/// let wg = WaitGroup::builder().drop_policy(DropPolicy::Warn).build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WaitGroupBuilder {
+    config: Config,
+}
+
+impl WaitGroupBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the policy when the last handle is dropped with nonzero count, default to
+    /// DropPolicy::Ignore.
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.config.drop_policy = policy;
+        self
+    }
+
+    /// Mark the WaitGroup poisoned when a guard is dropped by a panicking thread,
+    /// see [WaitGroup::wait_result()]. Default to false.
+    pub fn poison_on_panic(mut self, enable: bool) -> Self {
+        self.config.poison_on_panic = enable;
+        self
+    }
+
+    /// Set how a [CountError] is reported, default to ErrorPolicy::Panic.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.config.error_policy = policy;
+        self
+    }
+
+    /// Report an overflow when add() raises the count above `max`, the add() still takes
+    /// effect unless the policy panics. Without a ceiling, only the range of the counter is
+    /// checked, and an add() overflowing it is dropped.
+    pub fn max_count(mut self, max: usize) -> Self {
+        self.config.max_count = Some(max);
+        self
+    }
+
+    /// Set what wait_blocking() does inside a tokio runtime, default to
+    /// RuntimeBlockingPolicy::Allow.
+    #[cfg(feature = "tokio")]
+    pub fn runtime_blocking_policy(mut self, policy: RuntimeBlockingPolicy) -> Self {
+        self.config.runtime_blocking = policy;
+        self
+    }
+
+    /// Report misuse forbidden by the sync.WaitGroup of Go as [CountError::AddDuringWait]:
+    /// an add() from zero while a wait is in progress, which races the wait, or reuses the
+    /// WaitGroup before the previous wait returned. Default to false.
+    ///
+    /// Only the async waiting future is checked, not the blocking waits.
+    pub fn go_semantics(mut self, enable: bool) -> Self {
+        self.config.go_semantics = enable;
+        self
+    }
+
+    /// Install hooks on the transitions of the WaitGroup.
+    pub fn observer(mut self, observer: Arc<dyn WaitGroupObserver>) -> Self {
+        self.config.observer = ObserverSlot(Some(observer));
+        self
+    }
+
+    pub fn build(self) -> WaitGroup {
+        GenericWaitGroup(WaitGroupInner::new(self.config))
+    }
+}
@@ -0,0 +1,52 @@
+//! Randomized wake delays for stress testing, enabled with the `chaos` feature.
+//!
+//! Downstream code should tolerate spurious and late wakeups, as allowed by the Future contract.
+//! With this feature, a wakeup from done() may be duplicated or delivered late from another
+//! thread, and done() may be delayed before the count is decreased.
+
+use std::task::Waker;
+
+#[cfg(feature = "chaos")]
+use std::{thread, time::Duration};
+
+/// Delay done() before the count is decreased.
+#[cfg(feature = "chaos")]
+#[inline]
+pub(crate) fn jitter() {
+    match fastrand::u8(..4) {
+        0 => thread::yield_now(),
+        1 => thread::sleep(Duration::from_micros(fastrand::u64(..200))),
+        _ => {}
+    }
+}
+
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub(crate) fn jitter() {}
+
+/// Wake the waiter, possibly twice or late.
+#[cfg(feature = "chaos")]
+#[inline]
+pub(crate) fn wake(waker: &Waker) {
+    match fastrand::u8(..4) {
+        0 => {
+            waker.wake_by_ref();
+            waker.wake_by_ref();
+        }
+        1 => {
+            let waker = waker.clone();
+            let delay = Duration::from_micros(fastrand::u64(..5000));
+            thread::spawn(move || {
+                thread::sleep(delay);
+                waker.wake();
+            });
+        }
+        _ => waker.wake_by_ref(),
+    }
+}
+
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub(crate) fn wake(waker: &Waker) {
+    waker.wake_by_ref();
+}
@@ -0,0 +1,155 @@
+//! Drop-in replacements for the API of the `awaitgroup` and `waitgroup` crates, enabled with
+//! the `compat` feature, so that migrating is a change of the import.
+//!
+//! The workers are guards of a [crate::WaitGroup], whose features are reachable through
+//! `inner()`.
+
+use crate::{
+    storage::{RawWaitGroup, Storage},
+    GenericWaitGroupGuard, WaitGroupGuard,
+};
+
+#[inline]
+#[track_caller]
+fn clone_guard(guard: &WaitGroupGuard) -> WaitGroupGuard {
+    let armed = guard.inner.raw().add(1);
+    GenericWaitGroupGuard::with_armed(guard.inner.clone(), armed)
+}
+
+/// The API of the `awaitgroup` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::compat::awaitgroup::WaitGroup;
+/// use tokio::runtime::Runtime;
+///
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let mut wg = WaitGroup::new();
+///     for _ in 0..4 {
+///         let worker = wg.worker();
+///         tokio::spawn(async move {
+///             // Do something
+///             worker.done();
+///         });
+///     }
+///     wg.wait().await;
+/// });
+/// ```
+pub mod awaitgroup {
+    use super::clone_guard;
+
+    /// Wait for the [Worker]s to be done.
+    #[derive(Default)]
+    pub struct WaitGroup(crate::WaitGroup);
+
+    /// Done on done() or drop, cloning adds another worker.
+    #[must_use = "dropping the worker marks it done"]
+    pub struct Worker(crate::WaitGroupGuard);
+
+    impl WaitGroup {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Add a worker
+        #[inline]
+        #[track_caller]
+        pub fn worker(&self) -> Worker {
+            Worker(self.0.add_guard())
+        }
+
+        /// Wait until every worker is done.
+        pub async fn wait(&mut self) {
+            self.0.wait().await;
+        }
+
+        /// The WaitGroup behind it
+        #[inline]
+        pub fn inner(&self) -> &crate::WaitGroup {
+            &self.0
+        }
+    }
+
+    impl Worker {
+        /// Mark the worker done
+        #[inline]
+        pub fn done(self) {}
+    }
+
+    impl Clone for Worker {
+        #[inline]
+        #[track_caller]
+        fn clone(&self) -> Self {
+            Worker(clone_guard(&self.0))
+        }
+    }
+}
+
+/// The API of the `waitgroup` crate.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::compat::waitgroup::WaitGroup;
+/// use tokio::runtime::Runtime;
+///
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let wg = WaitGroup::new();
+///     for _ in 0..4 {
+///         let worker = wg.worker();
+///         tokio::spawn(async move {
+///             // Do something
+///             drop(worker);
+///         });
+///     }
+///     wg.wait().await;
+/// });
+/// ```
+pub mod waitgroup {
+    use super::clone_guard;
+
+    /// Wait for the [Worker]s to be dropped.
+    #[derive(Default)]
+    pub struct WaitGroup(crate::WaitGroup);
+
+    /// Done on drop, cloning adds another worker.
+    #[must_use = "dropping the worker marks it done"]
+    pub struct Worker(crate::WaitGroupGuard);
+
+    impl WaitGroup {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Add a worker
+        #[inline]
+        #[track_caller]
+        pub fn worker(&self) -> Worker {
+            Worker(self.0.add_guard())
+        }
+
+        /// Wait until every worker is dropped.
+        pub async fn wait(self) {
+            self.0.wait().await;
+        }
+
+        /// The WaitGroup behind it
+        #[inline]
+        pub fn inner(&self) -> &crate::WaitGroup {
+            &self.0
+        }
+    }
+
+    impl Clone for Worker {
+        #[inline]
+        #[track_caller]
+        fn clone(&self) -> Self {
+            Worker(clone_guard(&self.0))
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::{Arc, OnceLock},
+    task::Waker,
+    thread,
+    time::Instant,
+};
+
+use parking_lot::{Condvar, Mutex};
+
+/*
+
+One driver thread per process expires the timed waits of all WaitGroups,
+instead of each wait allocating its own timer.
+
+The thread is spawned on first use, and lives as long as the process.
+
+*/
+static DRIVER: OnceLock<DeadlineDriver> = OnceLock::new();
+
+/// Below this many entries the heap is not pruned of the canceled timers
+const PRUNE_MIN: usize = 64;
+
+pub(crate) struct DeadlineDriver {
+    pub(crate) shared: Arc<Shared>,
+}
+
+pub(crate) struct Shared {
+    pub(crate) state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+pub(crate) struct State {
+    /// Canceled timers are pruned once they outnumber the live ones
+    pub(crate) heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    pub(crate) wakers: HashMap<u64, Waker>,
+    seq: u64,
+}
+
+impl DeadlineDriver {
+    #[inline]
+    pub(crate) fn get() -> &'static DeadlineDriver {
+        DRIVER.get_or_init(DeadlineDriver::new)
+    }
+
+    fn new() -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+        });
+        let _shared = shared.clone();
+        thread::Builder::new()
+            .name("waitgroup-deadline".to_string())
+            .spawn(move || _shared.run())
+            .expect("spawn deadline driver");
+        Self { shared }
+    }
+
+    /// Returns: timer_id
+    pub(crate) fn register(&self, deadline: Instant, waker: Waker) -> u64 {
+        let mut state = self.shared.state.lock();
+        state.seq += 1;
+        let timer_id = state.seq;
+        let earliest = match state.heap.peek() {
+            Some(Reverse((first, _))) => deadline < *first,
+            None => true,
+        };
+        state.heap.push(Reverse((deadline, timer_id)));
+        state.wakers.insert(timer_id, waker);
+        if earliest {
+            self.shared.cond.notify_one();
+        }
+        timer_id
+    }
+
+    /// Replace the waker if the timer has not fired yet
+    pub(crate) fn update(&self, timer_id: u64, waker: &Waker) {
+        let mut state = self.shared.state.lock();
+        if let Some(old) = state.wakers.get_mut(&timer_id) {
+            if !old.will_wake(waker) {
+                *old = waker.clone();
+            }
+        }
+    }
+
+    /// Remove the waker, the heap entry is pruned along with the other canceled ones when
+    /// they outnumber the live timers, which keeps the cancel O(1) amortized.
+    pub(crate) fn cancel(&self, timer_id: u64) {
+        let mut state = self.shared.state.lock();
+        if state.wakers.remove(&timer_id).is_none() {
+            return;
+        }
+        if state.heap.len() > PRUNE_MIN && state.heap.len() > state.wakers.len() * 2 {
+            let State { heap, wakers, .. } = &mut *state;
+            heap.retain(|Reverse((_, timer_id))| wakers.contains_key(timer_id));
+        }
+    }
+}
+
+impl Shared {
+    fn run(&self) {
+        let mut expired = Vec::new();
+        let mut state = self.state.lock();
+        loop {
+            let now = Instant::now();
+            while let Some(Reverse((deadline, timer_id))) = state.heap.peek().copied() {
+                if deadline > now {
+                    break;
+                }
+                state.heap.pop();
+                if let Some(waker) = state.wakers.remove(&timer_id) {
+                    expired.push(waker);
+                }
+            }
+            if !expired.is_empty() {
+                // Do not hold the lock while waking
+                drop(state);
+                for waker in expired.drain(..) {
+                    waker.wake();
+                }
+                state = self.state.lock();
+                continue;
+            }
+            match state.heap.peek() {
+                Some(Reverse((deadline, _))) => {
+                    let deadline = *deadline;
+                    self.cond.wait_until(&mut state, deadline);
+                }
+                None => self.cond.wait(&mut state),
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Structured tracing of WaitGroup transitions, enabled with the `diagnostics` feature.
+//!
+//! Every add / done / set_waker / cancel is emitted with a global sequence number and the
+//! calling thread id, to reconstruct the exact interleaving across threads. Transitions are
+//! logged at trace level with the `atomic_waitgroup::diagnostics` target, and passed to the
+//! hook installed by [set_hook()].
+//!
+//! The sequence number is taken right after the atomic operation of the transition, so for
+//! transitions on the same counter its order matches the order of the operations, except when
+//! two threads race between the operation and the numbering.
+
+#[cfg(feature = "diagnostics")]
+use std::{
+    fmt,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+    thread::{self, ThreadId},
+    time::Instant,
+};
+
+#[cfg(feature = "diagnostics")]
+use parking_lot::{const_rwlock, RwLock};
+
+/// The operation of a transition
+#[cfg_attr(not(feature = "diagnostics"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add(usize),
+    Done(usize),
+    SetWaker { waker_id: u64, wake_at: u64 },
+    Cancel { waker_id: u64 },
+}
+
+/// A transition of a WaitGroup
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// Global sequence number, increasing across all WaitGroups
+    pub seq: u64,
+    pub thread: ThreadId,
+    /// When the transition was emitted
+    pub at: Instant,
+    /// Address of the shared state, identifies the WaitGroup and its clones
+    pub group: usize,
+    pub op: Op,
+    /// Count after the transition
+    pub left: u64,
+    /// Call site of the transition, the WaitGroup internals for cancel
+    pub location: &'static Location<'static>,
+}
+
+#[cfg(feature = "diagnostics")]
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {:?} group={:#x} {:?} left={} at {}",
+            self.seq, self.thread, self.group, self.op, self.left, self.location
+        )
+    }
+}
+
+/// Convert transitions collected by the hook into the Chrome trace-event JSON format, to be
+/// loaded in chrome://tracing or Perfetto.
+///
+/// Each WaitGroup has a counter track of its count, add / done are instant events on the
+/// thread which called them, and each wait is an async span from set_waker to cancel.
+/// Timestamps are relative to the first transition.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::diagnostics::{self, Transition};
+/// use atomic_waitgroup::WaitGroup;
+/// use std::sync::Mutex;
+///
+/// static LOG: Mutex<Vec<Transition>> = Mutex::new(Vec::new());
+///
+/// diagnostics::set_hook(Some(|t| LOG.lock().unwrap().push(t.clone())));
+/// let wg = WaitGroup::new();
+/// wg.add(1);
+/// wg.done();
+/// diagnostics::set_hook(None);
+/// let json = diagnostics::to_chrome_trace(&LOG.lock().unwrap());
+/// assert!(json.contains(r#""name":"done 1""#));
+/// ```
+#[cfg(feature = "diagnostics")]
+pub fn to_chrome_trace(transitions: &[Transition]) -> String {
+    use std::fmt::Write;
+
+    let Some(start) = transitions.iter().map(|t| t.at).min() else {
+        return "[]".to_string();
+    };
+    // ThreadId has no stable numeric form, number them by appearance
+    let mut threads: Vec<ThreadId> = Vec::new();
+    let mut out = String::from("[");
+    for t in transitions {
+        let tid = match threads.iter().position(|id| *id == t.thread) {
+            Some(i) => i + 1,
+            None => {
+                threads.push(t.thread);
+                threads.len()
+            }
+        };
+        let ts = t.at.duration_since(start).as_nanos() as f64 / 1000.0;
+        let common = format!(
+            r#""ts":{:.3},"pid":1,"tid":{},"args":{{"seq":{},"left":{},"location":"{}"}}"#,
+            ts,
+            tid,
+            t.seq,
+            t.left,
+            escape(&t.location.to_string())
+        );
+        if out.len() > 1 {
+            out.push(',');
+        }
+        let _ = match t.op {
+            Op::Add(count) => write!(
+                out,
+                r#"{{"name":"add {}","cat":"{:#x}","ph":"i","s":"t",{}}},"#,
+                count, t.group, common
+            ),
+            Op::Done(count) => write!(
+                out,
+                r#"{{"name":"done {}","cat":"{:#x}","ph":"i","s":"t",{}}},"#,
+                count, t.group, common
+            ),
+            Op::SetWaker { waker_id, wake_at } => write!(
+                out,
+                r#"{{"name":"wait to {}","cat":"{:#x}","ph":"b","id":"{:#x}-{}",{}}},"#,
+                wake_at, t.group, t.group, waker_id, common
+            ),
+            Op::Cancel { waker_id } => write!(
+                out,
+                r#"{{"name":"wait","cat":"{:#x}","ph":"e","id":"{:#x}-{}",{}}},"#,
+                t.group, t.group, waker_id, common
+            ),
+        };
+        let _ = write!(
+            out,
+            r#"{{"name":"left {:#x}","ph":"C","ts":{:.3},"pid":1,"args":{{"left":{}}}}}"#,
+            t.group, ts, t.left
+        );
+    }
+    out.push(']');
+    out
+}
+
+#[cfg(feature = "diagnostics")]
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(feature = "diagnostics")]
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "diagnostics")]
+static HOOK: RwLock<Option<fn(&Transition)>> = const_rwlock(None);
+
+/// Install a hook called on every transition, or remove it with None.
+///
+/// The hook is called on the thread of the transition, it should not block.
+#[cfg(feature = "diagnostics")]
+pub fn set_hook(hook: Option<fn(&Transition)>) {
+    *HOOK.write() = hook;
+}
+
+#[cfg(feature = "diagnostics")]
+#[inline]
+pub(crate) fn emit<T>(group: &T, op: Op, left: u64, location: &'static Location<'static>) {
+    let transition = Transition {
+        seq: SEQ.fetch_add(1, Ordering::SeqCst),
+        thread: thread::current().id(),
+        at: Instant::now(),
+        group: group as *const T as usize,
+        op,
+        left,
+        location,
+    };
+    log::trace!(target: "atomic_waitgroup::diagnostics", "{}", transition);
+    if let Some(hook) = *HOOK.read() {
+        hook(&transition);
+    }
+}
+
+#[cfg(not(feature = "diagnostics"))]
+use std::panic::Location;
+
+#[cfg(not(feature = "diagnostics"))]
+#[inline(always)]
+pub(crate) fn emit<T>(_group: &T, _op: Op, _left: u64, _location: &'static Location<'static>) {}
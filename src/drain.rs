@@ -0,0 +1,185 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::{inner::SharedArc, WaitGroupInner};
+
+/// Phase of the shutdown of a WaitGroup, returned by [crate::WaitGroup::state()].
+///
+/// Only moves forward, Closed is skipped if the count is zero when closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DrainState {
+    /// add() is accepted
+    Open,
+    /// Closed by close() or claim_at(), the count only goes down
+    Closed,
+    /// Closed with zero count
+    Drained,
+}
+
+impl DrainState {
+    #[inline]
+    pub(crate) fn of(inner: &WaitGroupInner) -> Self {
+        if !inner.is_closed() {
+            DrainState::Open
+        } else if inner.left.load(Ordering::SeqCst) == 0 {
+            DrainState::Drained
+        } else {
+            DrainState::Closed
+        }
+    }
+}
+
+/// Tasks watching the DrainState
+pub(crate) struct DrainWatchers {
+    next_id: AtomicU64,
+    wakers: Mutex<Vec<(u64, Waker)>>,
+}
+
+impl DrainWatchers {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &DrainWatchers {
+        inner.drain.get_or_init(|| DrainWatchers {
+            next_id: AtomicU64::new(1),
+            wakers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Called after the state changed
+    pub(crate) fn notify(&self, inner: &WaitGroupInner) {
+        let wakers = std::mem::take(&mut *self.wakers.lock());
+        inner.wake_all(wakers.into_iter().map(|(_, waker)| waker));
+    }
+}
+
+/// Watch the [DrainState] of a WaitGroup, created by [crate::WaitGroup::watch_state()].
+///
+/// Any number of watches is allowed, they do not count as WaitGroup handles.
+///
+/// # NOTE
+///
+/// * The last done() from done_from_isr() notifies Drained only once flushed, see
+///   [WaitGroup::flush_isr()](crate::WaitGroup::flush_isr).
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{DrainState, WaitGroup};
+/// use tokio::runtime::Runtime;
+///
+/// let wg = WaitGroup::new();
+/// let mut watch = wg.watch_state();
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let guard = wg.add_guard();
+///     let th = tokio::spawn(async move {
+///         let mut phases = Vec::new();
+///         while let Some(state) = watch.changed().await {
+///             phases.push(state);
+///         }
+///         phases
+///     });
+///     wg.close();
+///     drop(guard);
+///     let phases = th.await.unwrap();
+///     assert_eq!(phases.last(), Some(&DrainState::Drained));
+/// });
+/// ```
+pub struct DrainWatch {
+    inner: SharedArc<WaitGroupInner>,
+    last: DrainState,
+}
+
+impl DrainWatch {
+    #[inline]
+    pub(crate) fn new(inner: SharedArc<WaitGroupInner>) -> Self {
+        DrainWatchers::get(&inner);
+        let last = DrainState::of(&inner);
+        Self { inner, last }
+    }
+
+    /// The state when last returned by changed(), or when the watch was created.
+    #[inline]
+    pub fn last(&self) -> DrainState {
+        self.last
+    }
+
+    /// Wait until the state differs from last(), return the new state, or None once Drained
+    /// has been returned.
+    ///
+    /// Canceling future is supported.
+    pub fn changed(&mut self) -> impl Future<Output = Option<DrainState>> + '_ {
+        ChangedFuture { watch: self, id: 0 }
+    }
+}
+
+struct ChangedFuture<'a> {
+    watch: &'a mut DrainWatch,
+    id: u64,
+}
+
+impl ChangedFuture<'_> {
+    #[inline]
+    fn check(&mut self) -> Option<DrainState> {
+        let state = DrainState::of(&self.watch.inner);
+        (state != self.watch.last).then(|| {
+            self.watch.last = state;
+            state
+        })
+    }
+
+    fn _clear(&mut self) {
+        if self.id == 0 {
+            return;
+        }
+        let id = self.id;
+        self.id = 0;
+        if let Some(watchers) = self.watch.inner.drain.get() {
+            watchers.wakers.lock().retain(|(_id, _)| *_id != id);
+        }
+    }
+}
+
+impl Drop for ChangedFuture<'_> {
+    fn drop(&mut self) {
+        self._clear();
+    }
+}
+
+impl Future for ChangedFuture<'_> {
+    type Output = Option<DrainState>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        if _self.watch.last == DrainState::Drained {
+            return Poll::Ready(None);
+        }
+        if let Some(state) = _self.check() {
+            _self._clear();
+            return Poll::Ready(Some(state));
+        }
+        let watchers = DrainWatchers::get(&_self.watch.inner);
+        {
+            let mut wakers = watchers.wakers.lock();
+            if _self.id == 0 {
+                _self.id = watchers.next_id.fetch_add(1, Ordering::Relaxed);
+            }
+            // notify() takes the wakers, push again on every poll
+            match wakers.iter_mut().find(|(id, _)| *id == _self.id) {
+                Some((_, waker)) => waker.clone_from(ctx.waker()),
+                None => wakers.push((_self.id, ctx.waker().clone())),
+            }
+        }
+        // The state changes before notify(), checking after the waker is pushed never misses it
+        if let Some(state) = _self.check() {
+            _self._clear();
+            return Poll::Ready(Some(state));
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+/// Returned when a timed wait reaches its deadline before the WaitGroup does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Returned by [crate::WaitGroup::wait_result()] when guards were dropped by panicking
+/// threads, with poison mode enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Poisoned {
+    /// Number of guards dropped while panicking
+    pub count: usize,
+}
+
+impl fmt::Display for Poisoned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WaitGroup poisoned by {} panicking guard(s)", self.count)
+    }
+}
+
+impl std::error::Error for Poisoned {}
+
+/// Misuse of the count detected at runtime, handled according to the
+/// [crate::ErrorPolicy] of the WaitGroup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CountError {
+    /// add() exceeding `max`, the configured ceiling or the range of the counter
+    Overflow {
+        left: usize,
+        count: usize,
+        max: usize,
+    },
+    /// done() of `count` larger than `left`, the counter is not changed
+    Underflow { left: usize, count: usize },
+    /// add() of `count` after the WaitGroup is closed by claim_at(), the counter is not changed
+    Closed { count: usize },
+    /// The waker of the waiting future panicked when woken, the panic is caught. Only logged
+    /// under [crate::ErrorPolicy::Panic].
+    WakerPanicked,
+    /// add() of `count` from zero while a wait is in progress, forbidden by the Go semantics
+    /// (see [crate::WaitGroupBuilder::go_semantics()]), the counter is still increased
+    AddDuringWait { count: usize },
+}
+
+impl fmt::Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow { left, count, max } => {
+                write!(
+                    f,
+                    "WaitGroup count overflow: {} + {} > {}",
+                    left, count, max
+                )
+            }
+            Self::Underflow { left, count } => {
+                write!(f, "WaitGroup count underflow: {} - {} < 0", left, count)
+            }
+            Self::Closed { count } => {
+                write!(f, "WaitGroup closed, add of {} rejected", count)
+            }
+            Self::WakerPanicked => write!(f, "WaitGroup waker panicked on wake"),
+            Self::AddDuringWait { count } => {
+                write!(f, "WaitGroup add of {} from zero during a wait", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CountError {}
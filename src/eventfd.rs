@@ -0,0 +1,68 @@
+use std::{
+    ffi::c_void,
+    marker::PhantomData,
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+    sync::Arc,
+};
+
+use log::error;
+
+use crate::listener::{Listener, ListenerHandle};
+
+/// Registration created by [crate::WaitGroup::register_eventfd()], stops notifying on drop.
+///
+/// Borrows the fd, so that it cannot be closed while registered.
+#[must_use = "dropping the registration stops the notification"]
+pub struct EventFdRegistration<'fd> {
+    _handle: ListenerHandle,
+    _fd: PhantomData<BorrowedFd<'fd>>,
+}
+
+struct EventFdListener {
+    fd: RawFd,
+    threshold: u64,
+}
+
+impl EventFdListener {
+    #[inline]
+    fn signal(&self) {
+        let val: u64 = 1;
+        let r = unsafe { libc::write(self.fd, &val as *const u64 as *const c_void, 8) };
+        // EAGAIN means the eventfd counter is saturated, the reader is signaled anyway
+        if r < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() != std::io::ErrorKind::WouldBlock {
+                error!("WaitGroup write eventfd {} error: {}", self.fd, e);
+            }
+        }
+    }
+}
+
+impl Listener for EventFdListener {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        if old > self.threshold && left <= self.threshold {
+            self.signal();
+        }
+    }
+}
+
+pub(crate) fn register<'fd>(
+    wg: &crate::WaitGroup,
+    fd: BorrowedFd<'fd>,
+    threshold: usize,
+) -> EventFdRegistration<'fd> {
+    let listener = Arc::new(EventFdListener {
+        fd: fd.as_raw_fd(),
+        threshold: threshold as u64,
+    });
+    let _handle = ListenerHandle::register(wg.0.clone(), listener.clone());
+    // Registered before checking, might signal twice but never miss
+    if wg.0.left.load(std::sync::atomic::Ordering::SeqCst) <= listener.threshold {
+        listener.signal();
+    }
+    EventFdRegistration {
+        _handle,
+        _fd: PhantomData,
+    }
+}
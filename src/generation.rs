@@ -0,0 +1,189 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::{error::CountError, inner::SharedArc, WaitGroupInner};
+
+struct State {
+    generation: u64,
+    /// Units of the guards of the current generation
+    left: u64,
+    wakers: Vec<(u64, Waker)>,
+}
+
+/// Generation of the guards created by add_generation_guard()
+pub(crate) struct Generations {
+    next_id: AtomicU64,
+    state: Mutex<State>,
+}
+
+impl Generations {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &Generations {
+        inner.generations.get_or_init(|| Generations {
+            next_id: AtomicU64::new(1),
+            state: Mutex::new(State {
+                generation: 0,
+                left: 0,
+                wakers: Vec::new(),
+            }),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn current(&self) -> u64 {
+        self.state.lock().generation
+    }
+
+    /// Start a generation, abandoning the guards of the current one, return the new one
+    #[track_caller]
+    pub(crate) fn advance(&self, inner: &WaitGroupInner) -> u64 {
+        let (generation, done) = self.advance_with(inner, |abandoned| {
+            let count = abandoned as usize;
+            (count > 0)
+                .then(|| inner.decrease(count).map(|left| (count, left)))
+                .flatten()
+        });
+        // Listeners and wakes outside the lock, they may call back into the generations
+        if let Some((count, left)) = done {
+            inner.decreased(count, left);
+        }
+        generation
+    }
+
+    /// Start a generation, calling `f` with the units of the abandoned guards under the lock,
+    /// so that a guard dropped concurrently is either counted by `f` or ignored.
+    /// Return the new generation and the result of `f`.
+    pub(crate) fn advance_with<R, F: FnOnce(u64) -> R>(
+        &self,
+        inner: &WaitGroupInner,
+        f: F,
+    ) -> (u64, R) {
+        let mut state = self.state.lock();
+        let abandoned = std::mem::take(&mut state.left);
+        state.generation += 1;
+        let generation = state.generation;
+        let wakers = std::mem::take(&mut state.wakers);
+        let r = f(abandoned);
+        drop(state);
+        inner.wake_all(wakers.into_iter().map(|(_, waker)| waker));
+        (generation, r)
+    }
+}
+
+/// Decrease the count on drop, unless a later generation was started by
+/// [crate::WaitGroup::next_generation()], created by [crate::WaitGroup::add_generation_guard()].
+///
+/// A guard outliving its generation is already taken off the count, its drop does not
+/// corrupt the generation in progress.
+#[must_use = "dropping the guard decreases the count"]
+pub struct GenerationGuard {
+    inner: SharedArc<WaitGroupInner>,
+    generation: u64,
+}
+
+impl GenerationGuard {
+    /// Return the error of the increase, without a guard, eg. closed by claim_at()
+    #[inline]
+    #[track_caller]
+    pub(crate) fn new(inner: SharedArc<WaitGroupInner>) -> Result<Self, CountError> {
+        let generation = {
+            let mut state = Generations::get(&inner).state.lock();
+            // Under the lock, so that reset() zeroes the count either before or after it
+            inner.increase(1)?;
+            state.left += 1;
+            state.generation
+        };
+        Ok(Self { inner, generation })
+    }
+
+    /// The generation the guard was created in
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Drop for GenerationGuard {
+    #[track_caller]
+    fn drop(&mut self) {
+        let generations = Generations::get(&self.inner);
+        let mut state = generations.state.lock();
+        if state.generation != self.generation {
+            return;
+        }
+        state.left -= 1;
+        self.inner.poison_check();
+        // Decreased under the lock, so that reset() zeroes the count either before or after it
+        let left = self.inner.decrease(1);
+        let wakers = match state.left {
+            0 => std::mem::take(&mut state.wakers),
+            _ => Vec::new(),
+        };
+        // Listeners and wakes outside the lock, they may call back into the generations
+        drop(state);
+        if let Some(left) = left {
+            self.inner.decreased(1, left);
+        }
+        self.inner
+            .wake_all(wakers.into_iter().map(|(_, waker)| waker));
+    }
+}
+
+/// Wait until the guards of `generation` are all dropped or abandoned
+pub(crate) struct GenerationFuture<'a> {
+    generations: &'a Generations,
+    generation: u64,
+    id: u64,
+}
+
+impl<'a> GenerationFuture<'a> {
+    #[inline]
+    pub(crate) fn new(generations: &'a Generations, generation: u64) -> Self {
+        Self {
+            generations,
+            generation,
+            id: 0,
+        }
+    }
+}
+
+impl Drop for GenerationFuture<'_> {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            let id = self.id;
+            let mut state = self.generations.state.lock();
+            state.wakers.retain(|(_id, _)| *_id != id);
+        }
+    }
+}
+
+impl Future for GenerationFuture<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        let mut state = _self.generations.state.lock();
+        if state.generation > _self.generation || state.left == 0 {
+            if _self.id != 0 {
+                let id = _self.id;
+                state.wakers.retain(|(_id, _)| *_id != id);
+                _self.id = 0;
+            }
+            return Poll::Ready(());
+        }
+        if _self.id == 0 {
+            _self.id = _self.generations.next_id.fetch_add(1, Ordering::Relaxed);
+        }
+        // The wakers are taken on wake, push again on every poll
+        match state.wakers.iter_mut().find(|(id, _)| *id == _self.id) {
+            Some((_, waker)) => waker.clone_from(ctx.waker()),
+            None => state.wakers.push((_self.id, ctx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::Cell;
+
+use crate::{storage::RawWaitGroup, GenericWaitGroup, Storage};
+
+/// Units reserved from a WaitGroup with one add(), handed out as tickets, created by
+/// [GenericWaitGroup::guard_set()].
+///
+/// Handing out a ticket touches neither the count nor a reference count, only dropping it
+/// decreases the count like a guard. The units not handed out are returned on drop.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::WaitGroup;
+///
+/// let wg = WaitGroup::new();
+/// let items: Vec<u32> = (0..100).collect();
+/// std::thread::scope(|s| {
+///     let set = wg.guard_set(items.len());
+///     for item in items.iter() {
+///         let ticket = set.ticket().unwrap();
+///         s.spawn(move || {
+///             // Do something with item
+///             let _ = item;
+///             drop(ticket);
+///         });
+///     }
+/// });
+/// assert_eq!(wg.left(), 0);
+/// ```
+pub struct GuardSet<'a, S: Storage> {
+    wg: &'a GenericWaitGroup<S>,
+    remaining: Cell<usize>,
+}
+
+/// A unit of a [GuardSet], decreases the count by one on drop.
+#[must_use = "dropping the ticket decreases the count"]
+pub struct Ticket<'a, S: Storage> {
+    wg: &'a GenericWaitGroup<S>,
+}
+
+/// Decrease the count of the borrowed WaitGroup on drop, created by
+/// [GenericWaitGroup::add_guard_ref()].
+///
+/// Like a guard, without cloning the handle, for work scoped within the lifetime of the
+/// WaitGroup.
+#[must_use = "dropping the guard decreases the count"]
+pub struct BorrowedGuard<'a, S: Storage> {
+    wg: &'a GenericWaitGroup<S>,
+    /// False if the add was rejected, eg. closed
+    armed: bool,
+}
+
+impl<'a, S: Storage> BorrowedGuard<'a, S> {
+    #[inline]
+    #[track_caller]
+    pub(crate) fn new(wg: &'a GenericWaitGroup<S>) -> Self {
+        let armed = wg.0.raw().add(1);
+        Self { wg, armed }
+    }
+
+    /// Return true if the guard holds its count, false if the add was rejected.
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+}
+
+impl<'a, S: Storage> GuardSet<'a, S> {
+    #[inline]
+    #[track_caller]
+    pub(crate) fn new(wg: &'a GenericWaitGroup<S>, count: usize) -> Self {
+        // Rejected, eg. closed, no ticket to hand out
+        let added = wg.0.raw().add(count);
+        Self {
+            wg,
+            remaining: Cell::new(if added { count } else { 0 }),
+        }
+    }
+
+    /// Take a unit from the set, None if all have been handed out.
+    #[inline]
+    pub fn ticket(&self) -> Option<Ticket<'a, S>> {
+        let remaining = self.remaining.get();
+        if remaining == 0 {
+            return None;
+        }
+        self.remaining.set(remaining - 1);
+        Some(Ticket { wg: self.wg })
+    }
+
+    /// Count of units not handed out yet
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining.get()
+    }
+}
+
+impl<S: Storage> Drop for GuardSet<'_, S> {
+    #[inline]
+    fn drop(&mut self) {
+        let remaining = self.remaining.get();
+        if remaining > 0 {
+            self.wg.0.raw().done(remaining);
+        }
+    }
+}
+
+impl<S: Storage> Drop for Ticket<'_, S> {
+    #[inline]
+    fn drop(&mut self) {
+        self.wg.0.raw().guard_done();
+    }
+}
+
+impl<S: Storage> Drop for BorrowedGuard<'_, S> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.armed {
+            self.wg.0.raw().guard_done();
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{fmt::Write, sync::atomic::Ordering, time::Duration};
+
+use crate::{storage::NO_WAITER, WaitGroup};
+
+/// Readiness of a WaitGroup for a probe handler, returned by [WaitGroup::readiness()].
+///
+/// The group is draining once a wait is in progress, eg. the shutdown waiting for in-flight
+/// requests, from then on the probe should fail so that the load balancer stops sending
+/// traffic.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::WaitGroup;
+///
+/// let wg = WaitGroup::new();
+/// let _guard = wg.add_guard();
+/// let readiness = wg.readiness();
+/// assert_eq!(readiness.http_status(), 200);
+/// assert_eq!(
+///     readiness.to_json(),
+///     r#"{"ready":true,"draining":false,"left":1,"oldest_age_ms":null}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Readiness {
+    /// A wait is in progress, async or blocking
+    pub draining: bool,
+    /// Count left at this moment
+    pub left: usize,
+    /// How long the oldest guard registered with the `diagnostics` feature has been
+    /// outstanding, always None without the feature.
+    pub oldest_age: Option<Duration>,
+}
+
+impl Readiness {
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        !self.draining
+    }
+
+    /// 200 if ready, otherwise 503
+    #[inline]
+    pub fn http_status(&self) -> u16 {
+        if self.is_ready() {
+            200
+        } else {
+            503
+        }
+    }
+
+    /// The payload as a JSON object, with the age in milliseconds.
+    pub fn to_json(&self) -> String {
+        let mut s = String::with_capacity(80);
+        let _ = write!(
+            s,
+            r#"{{"ready":{},"draining":{},"left":{},"oldest_age_ms":"#,
+            self.is_ready(),
+            self.draining,
+            self.left
+        );
+        match self.oldest_age {
+            Some(age) => {
+                let _ = write!(s, "{}}}", age.as_millis());
+            }
+            None => s.push_str("null}"),
+        }
+        s
+    }
+}
+
+pub(crate) fn readiness(wg: &WaitGroup) -> Readiness {
+    let inner = wg.0.as_ref();
+    let draining = inner.waiting.load(Ordering::Acquire) != NO_WAITER
+        || inner
+            .blocking
+            .get()
+            .is_some_and(|waiters| waiters.blocked() > 0);
+    #[cfg(feature = "diagnostics")]
+    let oldest_age = wg.oldest_outstanding_age();
+    #[cfg(not(feature = "diagnostics"))]
+    let oldest_age = None;
+    Readiness {
+        draining,
+        left: wg.left(),
+        oldest_age,
+    }
+}
@@ -0,0 +1,67 @@
+//! Injection points between the atomic steps of wait and done(), enabled with the
+//! `test-hooks` feature.
+//!
+//! The hook installed by [set_hook()] is called on the thread reaching a [Point], before the
+//! step is taken. Blocking in the hook parks the thread there, so that a test can script an
+//! exact interleaving of a wait and done(), eg. with channels or barriers, instead of sleeping.
+
+#[cfg(feature = "test-hooks")]
+use parking_lot::{const_rwlock, RwLock};
+
+/// A step of the wait / done() protocol
+#[cfg_attr(not(feature = "test-hooks"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Point {
+    /// A wait future loads the count, before registering its waker
+    LoadLeft,
+    /// A wait future sets its waker and the count to be woken at
+    SetWaiting,
+    /// A wait future loads the count again after registering
+    Recheck,
+    /// done() of WaitGroup decreases the count
+    Decrease,
+    /// done() of WaitGroup loads the count the waiter is woken at
+    LoadWaiting,
+}
+
+#[cfg(feature = "test-hooks")]
+static HOOK: RwLock<Option<fn(Point)>> = const_rwlock(None);
+
+/// Install a hook called at every [Point], or remove it with None.
+///
+/// The hook applies to every WaitGroup of the process.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::hooks::{self, Point};
+/// use std::sync::Mutex;
+///
+/// static POINTS: Mutex<Vec<Point>> = Mutex::new(Vec::new());
+///
+/// hooks::set_hook(Some(|point| POINTS.lock().unwrap().push(point)));
+/// let wg = atomic_waitgroup::WaitGroup::new();
+/// wg.add(1);
+/// wg.done();
+/// hooks::set_hook(None);
+/// assert_eq!(*POINTS.lock().unwrap(), [Point::Decrease, Point::LoadWaiting]);
+/// ```
+#[cfg(feature = "test-hooks")]
+pub fn set_hook(hook: Option<fn(Point)>) {
+    *HOOK.write() = hook;
+}
+
+#[cfg(feature = "test-hooks")]
+#[inline]
+pub(crate) fn point(point: Point) {
+    // Not under the lock, the hook may block
+    let hook = *HOOK.read();
+    if let Some(hook) = hook {
+        hook(point);
+    }
+}
+
+#[cfg(not(feature = "test-hooks"))]
+#[inline(always)]
+pub(crate) fn point(_point: Point) {}
@@ -0,0 +1,809 @@
+use log::{error, warn};
+use std::{
+    panic::{self, AssertUnwindSafe, Location},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    task::Waker,
+};
+
+use parking_lot::RwLock;
+
+#[cfg(feature = "diagnostics")]
+use crate::registry::Registry;
+#[cfg(feature = "stats")]
+use crate::stats::Gauge;
+#[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+use crate::wasm_wait::WasmWaiters;
+
+use crate::{
+    admission::AdmissionQueue,
+    band::BandWaiters,
+    blocking::BlockingWaiters,
+    builder::{Config, ErrorPolicy},
+    chaos,
+    deadline::DeadlineDriver,
+    diagnostics::{self, Op},
+    drain::DrainWatchers,
+    error::CountError,
+    generation::Generations,
+    hooks::{self, Point},
+    listener::Listener,
+    storage::{sealed::Sealed, RawWaitGroup, Storage, NO_WAITER},
+    waker_slot::WakerSlot,
+    DropPolicy,
+};
+
+/*
+
+NOTE: Multiple atomic operation must happen at the same order
+
+WaitGroupFuture |   done()
+----------
+left.load()     |   left -=1
+waiting = true  |   load_waiting
+left.load ()    |
+------------
+
+*/
+const OPEN: u8 = 0;
+/// claim_at() is comparing the count to its target
+const CLAIMING: u8 = 1;
+const CLOSED: u8 = 2;
+
+/// Run a wake, so that a panicking third-party waker does not unwind into done() or a guard
+/// drop, nor through the lock of the waker slot. Return false if it panicked.
+#[inline(always)]
+fn caught<F: FnOnce()>(wake: F) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(wake)).is_ok()
+}
+
+/// The Arc of the shared state, triomphe::Arc with the `triomphe` feature
+#[cfg(not(feature = "triomphe"))]
+pub(crate) use std::sync::Arc as SharedArc;
+#[cfg(feature = "triomphe")]
+pub(crate) use triomphe::Arc as SharedArc;
+
+pub struct WaitGroupInner {
+    pub(crate) config: Config,
+    /// Count of WaitGroup handles, guards excluded
+    pub(crate) handles: AtomicUsize,
+    pub(crate) left: AtomicU64,
+    /// Threshold of the waiter to wake at, NO_WAITER if none
+    pub(crate) waiting: AtomicU64,
+    pub(crate) waker: WakerSlot,
+    /// A wake skipped by done_from_isr() because the waker slot was locked
+    pub(crate) wake_pending: AtomicBool,
+    /// Count decreased by done_from_isr(), the rest of its done() path is left to flush_isr()
+    pub(crate) isr_deferred: AtomicU64,
+    pub(crate) waker_id: AtomicU64,
+    pub(crate) added: AtomicU64,
+    pub(crate) completed: AtomicU64,
+    pub(crate) poisoned: AtomicUsize,
+    /// Waking is deferred until unfreeze()
+    pub(crate) frozen: AtomicBool,
+    /// OPEN, CLAIMING or CLOSED, add() is rejected once closed by claim_at()
+    pub(crate) state: AtomicU8,
+    #[cfg(feature = "stats")]
+    pub(crate) gauge: Gauge,
+    /// Waits returned after the count reached the target
+    #[cfg(feature = "stats")]
+    pub(crate) waits_completed: AtomicU64,
+    /// Waits given up before the count reached the target
+    #[cfg(feature = "stats")]
+    pub(crate) waits_canceled: AtomicU64,
+    /// Wakes by done() finding the waker slot locked
+    #[cfg(feature = "stats")]
+    pub(crate) done_contended: AtomicU64,
+    /// Registrations and cancels of a wait finding the waker slot locked
+    #[cfg(feature = "stats")]
+    pub(crate) register_contended: AtomicU64,
+    pub(crate) has_listeners: AtomicBool,
+    pub(crate) listeners: RwLock<Vec<Arc<dyn Listener>>>,
+    pub(crate) blocking: OnceLock<Arc<BlockingWaiters>>,
+    pub(crate) admission: OnceLock<AdmissionQueue>,
+    pub(crate) drain: OnceLock<DrainWatchers>,
+    pub(crate) bands: OnceLock<BandWaiters>,
+    pub(crate) generations: OnceLock<Generations>,
+    #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+    pub(crate) wasm: OnceLock<Arc<WasmWaiters>>,
+    #[cfg(feature = "diagnostics")]
+    pub(crate) registry: Registry,
+}
+
+/// Where the waker slot is locked, for the contention stats
+#[derive(Clone, Copy)]
+enum LockSite {
+    /// Wake by done()
+    Done,
+    /// Register, update or cancel a wait
+    Register,
+}
+
+impl WaitGroupInner {
+    #[inline(always)]
+    pub(crate) fn new(mut config: Config) -> SharedArc<Self> {
+        config.observer = config.observer.or_default();
+        SharedArc::new(Self {
+            config,
+            handles: AtomicUsize::new(1),
+            left: AtomicU64::new(0),
+            waiting: AtomicU64::new(NO_WAITER),
+            waker: WakerSlot::new(),
+            wake_pending: AtomicBool::new(false),
+            isr_deferred: AtomicU64::new(0),
+            waker_id: AtomicU64::new(0),
+            added: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            poisoned: AtomicUsize::new(0),
+            frozen: AtomicBool::new(false),
+            state: AtomicU8::new(OPEN),
+            #[cfg(feature = "stats")]
+            gauge: Gauge::new(),
+            #[cfg(feature = "stats")]
+            waits_completed: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            waits_canceled: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            done_contended: AtomicU64::new(0),
+            #[cfg(feature = "stats")]
+            register_contended: AtomicU64::new(0),
+            has_listeners: AtomicBool::new(false),
+            listeners: RwLock::new(Vec::new()),
+            blocking: OnceLock::new(),
+            admission: OnceLock::new(),
+            drain: OnceLock::new(),
+            bands: OnceLock::new(),
+            generations: OnceLock::new(),
+            #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+            wasm: OnceLock::new(),
+            #[cfg(feature = "diagnostics")]
+            registry: Registry::new(),
+        })
+    }
+
+    /// The timer thread shared by all WaitGroups
+    #[inline]
+    pub(crate) fn deadlines(&self) -> &'static DeadlineDriver {
+        DeadlineDriver::get()
+    }
+
+    /// Access the waker slot, and take over the wake left by done_from_isr() while locked.
+    #[inline(always)]
+    pub(crate) fn with_waker<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, f: F) -> R {
+        let r = self.waker.with(f);
+        self.take_pending_wake();
+        r
+    }
+
+    /// Same as with_waker(), counting the contention of the lock at `site` with the `stats`
+    /// feature.
+    #[inline(always)]
+    fn with_waker_at<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, site: LockSite, f: F) -> R {
+        #[cfg(feature = "stats")]
+        let r = {
+            let mut f = Some(f);
+            match self.waker.try_with(|waker| (f.take().unwrap())(waker)) {
+                Some(r) => r,
+                None => {
+                    let contended = match site {
+                        LockSite::Done => &self.done_contended,
+                        LockSite::Register => &self.register_contended,
+                    };
+                    contended.fetch_add(1, Ordering::Relaxed);
+                    self.waker.with(f.take().unwrap())
+                }
+            }
+        };
+        #[cfg(not(feature = "stats"))]
+        let r = {
+            let _ = site;
+            self.waker.with(f)
+        };
+        self.take_pending_wake();
+        r
+    }
+
+    #[inline(always)]
+    fn take_pending_wake(&self) {
+        if self.wake_pending.load(Ordering::SeqCst)
+            && self.wake_pending.swap(false, Ordering::SeqCst)
+        {
+            self.wake();
+        }
+    }
+
+    /// Wake the wakers taken out of a lock, so that a waker calling back into the WaitGroup
+    /// does not deadlock, each caught so that a panicking one does not skip the others.
+    pub(crate) fn wake_all<I: IntoIterator<Item = Waker>>(&self, wakers: I) {
+        let mut woken = true;
+        for waker in wakers {
+            woken &= caught(|| waker.wake());
+        }
+        if !woken {
+            self.report(CountError::WakerPanicked);
+        }
+    }
+
+    #[inline(always)]
+    fn wake(&self) {
+        let woken = self.waker.with(|waker| {
+            waker
+                .as_ref()
+                .is_none_or(|waker| caught(|| waker.wake_by_ref()))
+        });
+        if !woken {
+            self.report(CountError::WakerPanicked);
+        }
+    }
+
+    /// Never block on lock: when the waker slot is contended, leave the wake to the lock holder.
+    /// The rest of the done() path is deferred to flush_isr().
+    #[inline]
+    #[track_caller]
+    pub(crate) fn done_from_isr(&self, count: usize) {
+        let Some(left) = self.decrease(count) else {
+            return;
+        };
+        self.completed.fetch_add(count as u64, Ordering::Relaxed);
+        self.isr_deferred.fetch_add(count as u64, Ordering::AcqRel);
+        let waiting = self.waiting.load(Ordering::Acquire);
+        if waiting == NO_WAITER || left > waiting || self.frozen.load(Ordering::SeqCst) {
+            return;
+        }
+        let woken = self.waker.try_with(|waker| {
+            waker
+                .as_ref()
+                .is_none_or(|waker| caught(|| waker.wake_by_ref()))
+        });
+        let woken = match woken {
+            Some(woken) => woken,
+            None => {
+                #[cfg(feature = "stats")]
+                self.done_contended.fetch_add(1, Ordering::Relaxed);
+                self.wake_pending.store(true, Ordering::SeqCst);
+                // The holder might have checked the flag before it was set, retry once.
+                // If still contended, the new holder will see the flag after releasing.
+                self.waker
+                    .try_with(|waker| {
+                        !self.wake_pending.swap(false, Ordering::SeqCst)
+                            || waker
+                                .as_ref()
+                                .is_none_or(|waker| caught(|| waker.wake_by_ref()))
+                    })
+                    .unwrap_or(true)
+            }
+        };
+        if !woken {
+            self.report(CountError::WakerPanicked);
+        }
+    }
+
+    /// Run the done() path deferred by done_from_isr(), with the current count.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn flush_isr(&self) {
+        if self.isr_deferred.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let count = self.isr_deferred.swap(0, Ordering::AcqRel);
+        if count > 0 {
+            self.notify_decreased(count as usize, self.left.load(Ordering::SeqCst));
+        }
+    }
+
+    /// Decrease the count by CAS, return the count left, or None if it would go below zero.
+    /// The underflow is reported and the count is not changed.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn decrease(&self, count: usize) -> Option<u64> {
+        let mut old = self.left.load(Ordering::Acquire);
+        loop {
+            let Some(left) = old.checked_sub(count as u64) else {
+                self.report(CountError::Underflow {
+                    left: old as usize,
+                    count,
+                });
+                return None;
+            };
+            match self
+                .left
+                .compare_exchange_weak(old, left, Ordering::SeqCst, Ordering::Acquire)
+            {
+                Ok(_) => return Some(left),
+                Err(cur) => old = cur,
+            }
+        }
+    }
+
+    /// The count is at or below `target` and waking is not frozen
+    #[inline]
+    pub(crate) fn released(&self, target: u64) -> bool {
+        self.left.load(Ordering::SeqCst) <= target && !self.frozen.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn unfreeze(&self) {
+        self.frozen.store(false, Ordering::SeqCst);
+        self.wake_waiters();
+    }
+
+    /// Wake the waiters whose condition is true, without a done()
+    fn wake_waiters(&self) {
+        let left = self.left.load(Ordering::SeqCst);
+        let woken = self.with_waker(|waker| {
+            let waiting = self.waiting.load(Ordering::Acquire);
+            waiting == NO_WAITER
+                || left > waiting
+                || waker
+                    .as_ref()
+                    .is_none_or(|waker| caught(|| waker.wake_by_ref()))
+        });
+        if !woken {
+            self.report(CountError::WakerPanicked);
+        }
+        if let Some(blocking) = self.blocking.get() {
+            blocking.on_done(left, left);
+        }
+        #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+        if let Some(wasm) = self.wasm.get() {
+            wasm.on_done(left, left);
+        }
+    }
+
+    /// Wake every waiter to re-check its condition, whether it is true or not
+    pub(crate) fn notify_waiters(&self) {
+        if self.waiting.load(Ordering::Acquire) != NO_WAITER {
+            self.wake();
+        }
+        if let Some(blocking) = self.blocking.get() {
+            blocking.notify_all();
+        }
+        #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+        if let Some(wasm) = self.wasm.get() {
+            let left = self.left.load(Ordering::SeqCst);
+            wasm.on_done(left, left);
+        }
+        if let Some(admission) = self.admission.get() {
+            admission.grant(self);
+        }
+        if let Some(bands) = self.bands.get() {
+            bands.notify_all(self);
+        }
+    }
+
+    /// Zero the count and wake every waiter, abandoning the outstanding guards
+    pub(crate) fn reset(&self) {
+        let clear = || {
+            self.left.store(0, Ordering::SeqCst);
+            // Not reset by the satisfied future, it may be polled after a newer wait().
+            // The id is made stale, so that a pending future registers again on its next poll.
+            self.with_waker_at(LockSite::Register, |guard| {
+                self.waker_id.fetch_add(1, Ordering::SeqCst);
+                self.waiting.store(NO_WAITER, Ordering::Release);
+                guard.take()
+            })
+        };
+        let waker = match self.generations.get() {
+            Some(generations) => generations.advance_with(self, |_| clear()).1,
+            None => clear(),
+        };
+        if let Some(waker) = waker {
+            if !caught(|| waker.wake()) {
+                self.report(CountError::WakerPanicked);
+            }
+        }
+        self.notify_waiters();
+        if let Some(drain) = self.drain.get() {
+            drain.notify(self);
+        }
+    }
+
+    /// Increase the count, return the error without reporting it if the count is not changed.
+    /// Exceeding the configured ceiling is reported, and still counted.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn increase(&self, count: usize) -> Result<(), CountError> {
+        self.increase_from(count, u64::MAX).map(|_| ())
+    }
+
+    /// Increase the count only if it is at or below `from`, in the same CAS.
+    /// Return Ok(false) if it is above.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn increase_from(&self, count: usize, from: u64) -> Result<bool, CountError> {
+        self.increase_with(count, |old| (old <= from).then_some(count))
+            .map(|added| added.is_some())
+    }
+
+    /// increase_from() by the admission queue, which holds its lock: an increase lost to
+    /// claim_at() does not grant the queue again, the queue rejects the rest itself.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn increase_from_queued(&self, count: usize, from: u64) -> Result<bool, CountError> {
+        self.increase_core(count, |old| (old <= from).then_some(count), false)
+            .map(|added| added.is_some())
+    }
+
+    /// Increase the count by what `f` returns for the current count, in the same CAS.
+    /// Return Ok(None) if `f` returns None. `requested` is for the Closed error.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn increase_with<F: Fn(u64) -> Option<usize>>(
+        &self,
+        requested: usize,
+        f: F,
+    ) -> Result<Option<usize>, CountError> {
+        self.increase_core(requested, f, true)
+    }
+
+    /// `regrant` is false from the admission queue, not to take its lock again
+    #[inline(always)]
+    #[track_caller]
+    fn increase_core<F: Fn(u64) -> Option<usize>>(
+        &self,
+        requested: usize,
+        f: F,
+        regrant: bool,
+    ) -> Result<Option<usize>, CountError> {
+        if self.is_closed() {
+            return Err(CountError::Closed { count: requested });
+        }
+        let mut old = self.left.load(Ordering::Acquire);
+        let (left, count) = loop {
+            let Some(count) = f(old) else {
+                return Ok(None);
+            };
+            let Some(left) = old
+                .checked_add(count as u64)
+                .filter(|left| *left <= usize::MAX as u64)
+            else {
+                // Dropped, the count would be corrupted
+                return Err(CountError::Overflow {
+                    left: old as usize,
+                    count,
+                    max: usize::MAX,
+                });
+            };
+            match self
+                .left
+                .compare_exchange_weak(old, left, Ordering::SeqCst, Ordering::Acquire)
+            {
+                Ok(_) => break (left, count),
+                Err(cur) => old = cur,
+            }
+        };
+        if self.state.load(Ordering::SeqCst) != OPEN && self.settled_state() == CLOSED {
+            // Lost to claim_at(), which may have seen the increase, it only ever goes down
+            let left = self.left.fetch_sub(count as u64, Ordering::SeqCst) - count as u64;
+            if !self.frozen.load(Ordering::SeqCst) {
+                self.wake_waiters();
+            }
+            if let (true, Some(admission)) = (regrant, self.admission.get()) {
+                admission.grant(self);
+            }
+            if let (0, Some(drain)) = (left, self.drain.get()) {
+                drain.notify(self);
+            }
+            return Err(CountError::Closed { count });
+        }
+        if self.config.go_semantics && old == 0 && self.waiting.load(Ordering::Acquire) != NO_WAITER
+        {
+            self.report(CountError::AddDuringWait { count });
+        }
+        if let Some(max) = self.config.max_count {
+            if left as usize > max {
+                self.report(CountError::Overflow {
+                    left: old as usize,
+                    count,
+                    max,
+                });
+            }
+        }
+        self.added.fetch_add(count as u64, Ordering::Relaxed);
+        #[cfg(feature = "stats")]
+        self.gauge.update(left);
+        diagnostics::emit(self, Op::Add(count), left, Location::caller());
+        if let Some(observer) = self.config.observer.0.as_ref() {
+            observer.on_add(count, left as usize);
+        }
+        if let Some(bands) = self.bands.get() {
+            bands.notify(self, left);
+        }
+        Ok(Some(count))
+    }
+
+    /// done() of a guard of `count`
+    #[inline]
+    #[track_caller]
+    pub(crate) fn guard_done_many(&self, count: usize) {
+        self.poison_check();
+        self.done(count);
+    }
+
+    /// Count a guard dropped by a panicking thread, with poison mode enabled
+    #[inline(always)]
+    pub(crate) fn poison_check(&self) {
+        if self.config.poison_on_panic && std::thread::panicking() {
+            self.poisoned.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// The rest of done() once the count is decreased to `left`: listeners, observer and
+    /// wakes. Split so that the decrease can happen under a lock, and the user code after it.
+    #[inline]
+    #[track_caller]
+    pub(crate) fn decreased(&self, count: usize, left: u64) {
+        self.completed.fetch_add(count as u64, Ordering::Relaxed);
+        self.notify_decreased(count, left);
+    }
+
+    /// decreased() without counting completed, shared with flush_isr()
+    #[inline]
+    #[track_caller]
+    fn notify_decreased(&self, count: usize, left: u64) {
+        diagnostics::emit(self, Op::Done(count), left, Location::caller());
+        #[cfg(feature = "stats")]
+        self.gauge.update(left);
+        hooks::point(Point::LoadWaiting);
+        let waiting = self.waiting.load(Ordering::Acquire);
+        if self.has_listeners.load(Ordering::Acquire) {
+            for listener in self.listeners.read().iter() {
+                listener.on_done(left + count as u64, left);
+            }
+        }
+        if let Some(admission) = self.admission.get() {
+            admission.grant(self);
+        }
+        if let Some(bands) = self.bands.get() {
+            bands.notify(self, left);
+        }
+        if let (0, Some(drain)) = (left, self.drain.get()) {
+            drain.notify(self);
+        }
+        if let Some(observer) = self.config.observer.0.as_ref() {
+            observer.on_done(count, left as usize);
+            if left == 0 {
+                observer.on_zero();
+            }
+        }
+        if waiting == NO_WAITER || self.frozen.load(Ordering::SeqCst) {
+            return;
+        }
+        // waiting is the threshold to wake at, not always the target of the waiter
+        if left <= waiting {
+            // Do not take waker, it may be false waken when done() happened before newer wait()
+            let woken = self.with_waker_at(LockSite::Done, |waker| {
+                waker
+                    .as_ref()
+                    .is_none_or(|waker| caught(|| chaos::wake(waker)))
+            });
+            if !woken {
+                self.report(CountError::WakerPanicked);
+            }
+        }
+    }
+
+    /// Close against add() if the count is at or below `target`, return false if it is not.
+    /// Already closed, only the count is compared.
+    pub(crate) fn try_close(&self, target: u64) -> bool {
+        loop {
+            match self
+                .state
+                .compare_exchange(OPEN, CLAIMING, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(CLOSED) => return self.left.load(Ordering::SeqCst) <= target,
+                Err(_) => std::thread::yield_now(),
+            }
+        }
+        // add() increases before checking the state, an add() missed here sees CLAIMING
+        // and waits for the decision
+        if self.left.load(Ordering::SeqCst) <= target {
+            self.state.store(CLOSED, Ordering::SeqCst);
+            // Reject the queued acquire()
+            if let Some(admission) = self.admission.get() {
+                admission.grant(self);
+            }
+            if let Some(drain) = self.drain.get() {
+                drain.notify(self);
+            }
+            true
+        } else {
+            self.state.store(OPEN, Ordering::SeqCst);
+            false
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == CLOSED
+    }
+
+    /// The state after claim_at() decided, to admit an add() already counted
+    #[inline]
+    fn settled_state(&self) -> u8 {
+        loop {
+            let state = self.state.load(Ordering::SeqCst);
+            if state != CLAIMING {
+                return state;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn report(&self, err: CountError) {
+        match &self.config.error_policy {
+            // Already caught, re-raising it from done() or a guard drop helps nobody
+            ErrorPolicy::Panic if err != CountError::WakerPanicked => {
+                error!("{} at {}", err, Location::caller());
+                panic!("{} at {}", err, Location::caller());
+            }
+            ErrorPolicy::Panic | ErrorPolicy::Log => error!("{} at {}", err, Location::caller()),
+            ErrorPolicy::Callback(f) => f(&err),
+        }
+    }
+
+    #[inline]
+    fn drop_handle(&self) {
+        if self.handles.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        let left = self.left.load(Ordering::Acquire);
+        if left == 0 {
+            return;
+        }
+        match self.config.drop_policy {
+            DropPolicy::Ignore => {}
+            DropPolicy::Warn => {
+                warn!("WaitGroup dropped with {} left", left);
+            }
+            DropPolicy::DebugPanic => {
+                error!("WaitGroup dropped with {} left", left);
+                // Panic while unwinding will abort
+                if cfg!(debug_assertions) && !std::thread::panicking() {
+                    panic!("WaitGroup dropped with {} left", left);
+                }
+            }
+        }
+    }
+}
+
+impl RawWaitGroup for WaitGroupInner {
+    const NAME: &'static str = "WaitGroup";
+
+    #[inline(always)]
+    fn load_left(&self) -> u64 {
+        self.left.load(Ordering::Acquire)
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    fn add(&self, count: usize) -> bool {
+        self.flush_isr();
+        match self.increase(count) {
+            Ok(()) => true,
+            Err(err) => {
+                self.report(err);
+                false
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    #[inline(always)]
+    fn guard_done(&self) {
+        self.guard_done_many(1);
+    }
+
+    #[inline]
+    #[track_caller]
+    fn done(&self, count: usize) -> bool {
+        self.flush_isr();
+        chaos::jitter();
+        hooks::point(Point::Decrease);
+        let Some(left) = self.decrease(count) else {
+            return false;
+        };
+        self.decreased(count, left);
+        true
+    }
+
+    #[inline]
+    fn set_waker(&self, waker: Waker, wake_at: u64, caller: &'static Location<'static>) -> u64 {
+        let waker_id = self.waker_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let old_target = self.with_waker_at(LockSite::Register, |guard| {
+            guard.replace(waker);
+            self.waiting.swap(wake_at, Ordering::SeqCst)
+        });
+        diagnostics::emit(
+            self,
+            Op::SetWaker { waker_id, wake_at },
+            self.left.load(Ordering::Acquire),
+            caller,
+        );
+        if old_target != NO_WAITER {
+            panic!(
+                "Concurrent wait() by multiple coroutines is not supported at {}",
+                caller
+            )
+        }
+        if let Some(observer) = self.config.observer.0.as_ref() {
+            observer.on_wait_start(wake_at as usize);
+        }
+        waker_id
+    }
+
+    #[inline]
+    fn update_waker(&self, waker_id: u64, waker: &Waker) -> bool {
+        self.with_waker_at(LockSite::Register, |guard| {
+            if self.waker_id.load(Ordering::Acquire) != waker_id {
+                return false;
+            }
+            match guard {
+                Some(old) if old.will_wake(waker) => {}
+                _ => *guard = Some(waker.clone()),
+            }
+            true
+        })
+    }
+
+    #[inline]
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    fn cancel_wait(&self, waker_id: u64, satisfied: bool) {
+        self.with_waker_at(LockSite::Register, |guard| {
+            // In case wait() is canceled, eg. tokio timeout, do not disrupt other thread wait()
+            if self.waker_id.load(Ordering::Acquire) == waker_id {
+                self.waiting.store(NO_WAITER, Ordering::Release);
+                let _ = guard.take();
+            }
+        });
+        #[cfg(feature = "stats")]
+        if satisfied {
+            self.waits_completed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.waits_canceled.fetch_add(1, Ordering::Relaxed);
+        }
+        let left = self.left.load(Ordering::Acquire);
+        diagnostics::emit(self, Op::Cancel { waker_id }, left, Location::caller());
+        if let Some(observer) = self.config.observer.0.as_ref() {
+            observer.on_wait_end(left as usize);
+        }
+    }
+}
+
+impl Sealed for SharedArc<WaitGroupInner> {}
+
+impl Storage for SharedArc<WaitGroupInner> {
+    type Raw = WaitGroupInner;
+
+    #[inline(always)]
+    fn create() -> Self {
+        WaitGroupInner::new(Config::default())
+    }
+
+    #[inline(always)]
+    fn create_like(&self) -> Self {
+        WaitGroupInner::new(self.config.clone())
+    }
+
+    #[inline(always)]
+    fn raw(&self) -> &WaitGroupInner {
+        self.as_ref()
+    }
+
+    #[inline(always)]
+    fn clone_handle(&self) {
+        self.handles.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn drop_handle(&self) {
+        WaitGroupInner::drop_handle(self);
+    }
+}
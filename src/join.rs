@@ -0,0 +1,57 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::task::{AbortHandle, JoinError, JoinHandle};
+
+use crate::WaitGroupGuard;
+
+/// A JoinHandle of a task tracked by a WaitGroup, created by [crate::WaitGroup::attach_to()].
+///
+/// The count is decreased when the task completes, whether the handle is awaited or not.
+/// Dropping the handle detaches the task like a JoinHandle, it stays tracked.
+pub struct TrackedJoinHandle<T> {
+    handle: JoinHandle<Result<T, JoinError>>,
+    abort: AbortHandle,
+}
+
+impl<T: Send + 'static> TrackedJoinHandle<T> {
+    /// Must be called within a tokio runtime
+    pub(crate) fn new(handle: JoinHandle<T>, guard: WaitGroupGuard) -> Self {
+        let abort = handle.abort_handle();
+        // Awaits the task on behalf of the owner of the handle
+        let handle = tokio::spawn(async move {
+            let r = handle.await;
+            drop(guard);
+            r
+        });
+        Self { handle, abort }
+    }
+}
+
+impl<T> TrackedJoinHandle<T> {
+    /// Abort the task, the count is decreased once it is stopped.
+    #[inline]
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Return true once the task is finished and the count is decreased.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl<T> Future for TrackedJoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.get_mut().handle).poll(ctx) {
+            Poll::Ready(r) => Poll::Ready(r.and_then(|r| r)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
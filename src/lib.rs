@@ -8,8 +8,17 @@
 //!
 //! * wait() & wait_to() can be canceled by tokio::time::timeout or futures::select!.
 //!
+//! * The wait futures are Send, and compose with join / race combinators taking IntoFuture,
+//!   eg. futures-concurrency. wait() & wait_to() return the named [Waiter], which is Unpin
+//!   and FusedFuture with the `futures-core` feature, the others are to be pinned to race in
+//!   a collection.
+//!
 //! * Assumes only one thread calls wait(). If multiple concurrent wait() is detected,
-//! will panic for this invalid usage.
+//!   will panic for this invalid usage.
+//!
+//! * `MultiWaitGroup<N>` allows up to N concurrent waiters, in fixed slots without allocation.
+//!
+//! * LocalWaitGroup shares the same API for single-threaded use, without atomic operations.
 //!
 //! * done() & wait() is allowed to called concurrently.
 //!
@@ -48,60 +57,185 @@
 //!
 //!
 
-use log::error;
 use std::{
+    ffi::c_void,
+    fmt,
     future::Future,
+    mem::ManuallyDrop,
+    ops::RangeInclusive,
+    panic::Location,
     pin::Pin,
-    sync::{
-        atomic::{AtomicI64, AtomicU64, Ordering},
-        Arc,
-    },
+    ptr,
+    sync::atomic::Ordering,
     task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
 };
 
-use parking_lot::Mutex;
-
-/*
+#[cfg(feature = "actix")]
+pub mod actor;
+mod adapter;
+mod admission;
+mod band;
+mod blocking;
+mod builder;
+mod chaos;
+#[cfg(feature = "compat")]
+pub mod compat;
+mod deadline;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(not(feature = "diagnostics"))]
+mod diagnostics;
+mod drain;
+mod error;
+#[cfg(all(unix, feature = "eventfd"))]
+mod eventfd;
+mod generation;
+mod guard_set;
+#[cfg(feature = "health")]
+mod health;
+#[cfg(feature = "test-hooks")]
+pub mod hooks;
+#[cfg(not(feature = "test-hooks"))]
+mod hooks;
+mod inner;
+#[cfg(feature = "tokio")]
+mod join;
+mod limiter;
+mod listener;
+mod local;
+mod map;
+mod merge;
+#[cfg(feature = "monitor")]
+pub mod monitor;
+mod multi;
+mod observer;
+mod outcome;
+mod pipeline;
+#[cfg(feature = "indicatif")]
+mod progress;
+#[cfg(feature = "diagnostics")]
+mod registry;
+mod scope;
+mod shutdown;
+mod snapshot;
+#[cfg(feature = "stats")]
+mod stats;
+#[cfg(feature = "statsd")]
+mod statsd;
+mod storage;
+#[cfg(feature = "tokio-util")]
+mod task_tracker;
+mod timer;
+mod track;
+mod waiter;
+mod waker_slot;
+#[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+mod wasm_wait;
+mod watchdog;
+mod weighted;
+#[cfg(all(windows, feature = "win-event"))]
+mod win_event;
 
-NOTE: Multiple atomic operation must happen at the same order
+pub use adapter::DoneFn;
+use admission::AdmissionFuture;
+use band::BandFuture;
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm-atomics")))]
+use blocking::BlockingWaiters;
+#[cfg(feature = "tokio")]
+pub use builder::RuntimeBlockingPolicy;
+pub use builder::{DropPolicy, ErrorPolicy, WaitGroupBuilder};
+pub use drain::{DrainState, DrainWatch};
+pub use error::{CountError, Elapsed, Poisoned};
+#[cfg(all(unix, feature = "eventfd"))]
+pub use eventfd::EventFdRegistration;
+pub use generation::GenerationGuard;
+use generation::{GenerationFuture, Generations};
+pub use guard_set::{BorrowedGuard, GuardSet, Ticket};
+#[cfg(feature = "health")]
+pub use health::Readiness;
+use hooks::Point;
+use inner::{SharedArc, WaitGroupInner};
+#[cfg(feature = "tokio")]
+pub use join::TrackedJoinHandle;
+pub use limiter::{AcquireFuture, AdaptiveLimiter};
+pub use local::{LocalWaitGroup, LocalWaitGroupGuard};
+pub use map::{KeyGuard, KeyStats, WaitGroupMap};
+#[cfg(feature = "monitor")]
+pub use monitor::MonitorRegistration;
+pub use multi::{MultiWaitGroup, MultiWaitGroupGuard};
+pub use observer::{set_default_observer, WaitGroupObserver};
+pub use outcome::WaitOutcome;
+pub use pipeline::{Pipeline, StageGuard};
+#[cfg(feature = "indicatif")]
+pub use progress::ProgressBarRegistration;
+#[cfg(feature = "diagnostics")]
+pub use registry::{GuardInfo, TaggedGuard};
+pub use scope::BlockingScope;
+pub use shutdown::ShutdownPlan;
+pub use snapshot::{CountDelta, CountSnapshot};
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+#[cfg(feature = "statsd")]
+pub use statsd::{StatsdExporter, StatsdRegistration};
+pub use storage::Storage;
+use storage::{RawWaitGroup, NO_WAITER, WAKE_ALWAYS};
+#[cfg(feature = "tokio-util")]
+pub use task_tracker::TrackerGuard;
+#[cfg(feature = "futures-timer")]
+pub use timer::FuturesTimer;
+use timer::Timeout;
+pub use timer::Timer;
+pub use track::Tracked;
+pub use waiter::Waiter;
+pub use watchdog::Watchdog;
+pub use weighted::WeightedGuard;
+#[cfg(all(windows, feature = "win-event"))]
+pub use win_event::EventHandleRegistration;
 
-WaitGroupFuture |   done()
-----------
-left.load()     |   left -=1
-waiting = true  |   load_waiting
-left.load ()    |
-------------
+/// The WaitGroup generic over its storage, use the [WaitGroup] or [LocalWaitGroup] alias.
+pub struct GenericWaitGroup<S: Storage>(S);
 
-*/
-pub struct WaitGroup(Arc<WaitGroupInner>);
+/// A WaitGroup shared between threads.
+pub type WaitGroup = GenericWaitGroup<SharedArc<WaitGroupInner>>;
 
 // do not allow multiple wait
-impl Clone for WaitGroup {
+impl<S: Storage> Clone for GenericWaitGroup<S> {
     fn clone(&self) -> Self {
+        self.0.clone_handle();
         Self(self.0.clone())
     }
 }
 
-impl WaitGroup {
+impl<S: Storage> Drop for GenericWaitGroup<S> {
+    fn drop(&mut self) {
+        self.0.drop_handle();
+    }
+}
+
+impl<S: Storage> Default for GenericWaitGroup<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Storage> GenericWaitGroup<S> {
     pub fn new() -> Self {
-        Self(WaitGroupInner::new())
+        Self(S::create())
     }
 
     /// Return the count left inside this WaitGroup
     #[inline(always)]
     pub fn left(&self) -> usize {
-        let count = self.0.left.load(Ordering::SeqCst);
-        if count < 0 {
-            error!("WaitGroup.left {} < 0", count);
-            panic!("WaitGroup.left {} < 0", count);
-        }
-        count as usize
+        self.0.raw().load_left() as usize
     }
 
     /// Add specified count.
     #[inline(always)]
+    #[track_caller]
     pub fn add(&self, i: usize) {
-        self.0.left.fetch_add(i as i64, Ordering::SeqCst);
+        self.0.raw().add(i);
     }
 
     /// Add one to the WaitGroup, return a guard to decrease the count on drop.
@@ -115,7 +249,7 @@ impl WaitGroup {
     ///
     /// let wg = WaitGroup::new();
     /// let rt = Runtime::new().unwrap();
-
+    ///
     /// rt.block_on(async move {
     ///     let _guard = wg.add_guard();
     ///     tokio::spawn(async move {
@@ -124,38 +258,180 @@ impl WaitGroup {
     ///     });
     ///     wg.wait().await;
     /// });
+    /// ```
+    ///
+    /// # NOTE
+    ///
+    /// * An add rejected by a non-panicking [ErrorPolicy], eg. closed or overflow, returns a
+    ///   released guard, see [GenericWaitGroupGuard::is_armed()], which decreases nothing.
+    #[inline(always)]
+    #[track_caller]
+    pub fn add_guard(&self) -> GenericWaitGroupGuard<S> {
+        let armed = self.0.raw().add(1);
+        GenericWaitGroupGuard::with_armed(self.0.clone(), armed)
+    }
+
+    /// Add one to the WaitGroup, return a guard borrowing it, which decreases the count on
+    /// drop, for scoped work where cloning the handle per item is overhead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// std::thread::scope(|s| {
+    ///     for _ in 0..4 {
+    ///         let guard = wg.add_guard_ref();
+    ///         s.spawn(move || {
+    ///             // Do something
+    ///             drop(guard);
+    ///         });
+    ///     }
+    ///     wg.wait_blocking();
+    /// });
+    /// ```
     #[inline(always)]
-    pub fn add_guard(&self) -> WaitGroupGuard {
-        self.0.left.fetch_add(1, Ordering::SeqCst);
-        WaitGroupGuard {
-            inner: self.0.clone(),
+    #[track_caller]
+    pub fn add_guard_ref(&self) -> BorrowedGuard<'_, S> {
+        BorrowedGuard::new(self)
+    }
+
+    /// Add `count` to the WaitGroup at once, to be handed out as tickets in a hot loop.
+    ///
+    /// See [GuardSet].
+    #[inline]
+    #[track_caller]
+    pub fn guard_set(&self, count: usize) -> GuardSet<'_, S> {
+        GuardSet::new(self, count)
+    }
+
+    /// Add one to the WaitGroup, return a future which decreases the count on completion
+    /// (or when dropped), to be spawned onto any executor.
+    #[inline]
+    pub fn track<F: Future>(&self, future: F) -> Tracked<F, GenericWaitGroupGuard<S>> {
+        Tracked::new(future, self.add_guard())
+    }
+
+    /// Check if at most `target` is left, for hand-rolled poll loops, never registers a waker.
+    ///
+    /// Ready once the count is reached and the WaitGroup is not frozen.
+    #[inline]
+    pub fn poll_ready(&self, target: usize) -> Poll<()> {
+        let _self = self.0.raw();
+        if _self.load_left() <= target as u64 && !_self.is_frozen() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
         }
     }
 
     /// Wait until specified count is left in the WaitGroup.
     ///
-    /// Return false means there's no waiting happened.
+    /// [WaitOutcome::blocked] false means there's no waiting happened, true means the blocking
+    /// actually happened. [WaitOutcome::left] is the count that satisfied the target, unlike
+    /// left() afterwards it does not race with a later add().
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    ///
+    /// * Polled with [Waker::noop()], eg. by an executor busy-polling, the waker is not
+    ///   registered.
+    #[track_caller]
+    pub fn wait_to(&self, target: usize) -> Waiter<'_, S> {
+        Waiter::new(&self.0, target, Location::caller())
+    }
+
+    /// Wait until specified count is left in the WaitGroup, invoking `progress` with the new
+    /// count each time the waiter observes it lower than before.
+    ///
+    /// Return false means there's no waiting happened (and `progress` is never called).
     ///
     /// Return true means the blocking actually happened.
     ///
     /// # NOTE
     ///
+    /// * The waiter is woken on every done() while registered, so this costs more than
+    ///   wait_to() under heavy traffic.
+    ///
+    /// * Consecutive done() calls may be observed as a single step.
+    ///
     /// * Only assume one waiting future at the same time, otherwise will panic.
     ///
     /// * Canceling future is supported.
-    pub async fn wait_to(&self, target: usize) -> bool {
-        let _self = self.0.as_ref();
-        let left = _self.left.load(Ordering::Acquire);
-        if left <= target as i64 {
-            return false;
-        }
-        WaitGroupFuture {
-            wg: &_self,
-            target,
-            waker_id: 0,
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    ///
+    /// rt.block_on(async move {
+    ///     for _ in 0..3 {
+    ///         let _guard = wg.add_guard();
+    ///         tokio::spawn(async move {
+    ///             // Do something
+    ///             drop(_guard);
+    ///         });
+    ///     }
+    ///     wg.wait_to_with_progress(0, |left| println!("{} left", left)).await;
+    /// });
+    #[track_caller]
+    pub fn wait_to_with_progress<'a, F: FnMut(usize) + 'a>(
+        &'a self,
+        target: usize,
+        progress: F,
+    ) -> impl Future<Output = bool> + 'a {
+        let caller = Location::caller();
+        async move {
+            let _self = self.0.raw();
+            let left = _self.load_left();
+            if left <= target as u64 && !_self.is_frozen() {
+                return false;
+            }
+            WaitGroupProgressFuture {
+                wg: _self,
+                target,
+                last: left,
+                progress,
+                waker_id: 0,
+                caller,
+            }
+            .await;
+            true
         }
-        .await;
-        return true;
+    }
+
+    /// Wait until specified count is left in the WaitGroup, or the timeout is reached with
+    /// the timer backend of the caller's executor.
+    ///
+    /// The [WaitOutcome] is the one of wait_to().
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    #[track_caller]
+    pub fn wait_to_timeout<'a, T: Timer>(
+        &'a self,
+        target: usize,
+        timeout: Duration,
+        timer: &T,
+    ) -> impl Future<Output = Result<WaitOutcome, Elapsed>> + 'a
+    where
+        T::Sleep: 'a,
+    {
+        let sleep = timer.sleep_until(Instant::now() + timeout);
+        Timeout::new(self.wait_to(target), sleep)
     }
 
     /// Wait until zero count in the WaitGroup.
@@ -166,167 +442,2092 @@ impl WaitGroup {
     ///
     /// * Canceling future is supported.
     #[inline(always)]
-    pub async fn wait(&self) {
-        self.wait_to(0).await;
+    #[track_caller]
+    pub fn wait(&self) -> Waiter<'_, S> {
+        Waiter::new(&self.0, 0, Location::caller())
+    }
+
+    /// Same as wait(), for the two-stage registration with [Waiter::enable()] before the
+    /// first poll.
+    #[inline]
+    #[track_caller]
+    pub fn waiter(&self) -> Waiter<'_, S> {
+        self.wait()
+    }
+
+    /// Same as wait_to(), for the two-stage registration with [Waiter::enable()] before the
+    /// first poll.
+    #[inline]
+    #[track_caller]
+    pub fn waiter_to(&self, target: usize) -> Waiter<'_, S> {
+        self.wait_to(target)
+    }
+
+    /// Wait until specified count is left in the WaitGroup, return how long the waiting
+    /// blocked, zero if no waiting happened.
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    #[cfg(feature = "stats")]
+    #[track_caller]
+    pub fn wait_to_timed(&self, target: usize) -> impl Future<Output = Duration> + '_ {
+        let f = self.wait_to(target);
+        async move {
+            let start = stats::Instant::now();
+            if f.await.blocked {
+                start.elapsed()
+            } else {
+                Duration::ZERO
+            }
+        }
+    }
+
+    /// Wait until zero count in the WaitGroup, return how long the waiting blocked.
+    ///
+    /// See wait_to_timed().
+    #[cfg(feature = "stats")]
+    #[inline(always)]
+    #[track_caller]
+    pub fn wait_timed(&self) -> impl Future<Output = Duration> + '_ {
+        self.wait_to_timed(0)
     }
 
     /// Decrease count by one.
     #[inline]
+    #[track_caller]
     pub fn done(&self) {
-        let inner = self.0.as_ref();
-        inner.done(1);
+        self.0.raw().done(1);
     }
 
     /// Decrease count by specified value
+    ///
+    /// # NOTE
+    ///
+    /// * If `count` is larger than the count left, the count is not changed, and the underflow
+    ///   is handled by the [ErrorPolicy] for WaitGroup, or panics for the others.
     #[inline]
+    #[track_caller]
     pub fn done_many(&self, count: usize) {
-        let inner = self.0.as_ref();
-        inner.done(count as i64);
+        self.0.raw().done(count);
+    }
+
+    /// Move `count` from this WaitGroup to `other`, for work items migrating between stages.
+    ///
+    /// `other` is increased before this WaitGroup is decreased, so the items are always counted
+    /// by at least one group: a waiter on either group is never woken by a transfer in flight.
+    ///
+    /// # NOTE
+    ///
+    /// * If the count of this WaitGroup is less than `count`, the underflow is handled like
+    ///   done_many(), and `other` is decreased back, also when the [ErrorPolicy] panics.
+    ///
+    /// * If `other` rejects the add, eg. closed, nothing is moved.
+    #[inline]
+    #[track_caller]
+    pub fn transfer(&self, count: usize, other: &Self) {
+        if !other.0.raw().add(count) {
+            return;
+        }
+        let undo = TransferUndo { wg: other, count };
+        if self.0.raw().done(count) {
+            std::mem::forget(undo);
+        }
+    }
+
+    /// Move `count` into a new WaitGroup, to wait on a subset of the work, or hand it over to
+    /// another owner.
+    ///
+    /// The moved count should then be decreased through the returned WaitGroup.
+    ///
+    /// The new WaitGroup has the options of this one, eg. [ErrorPolicy] and observer.
+    ///
+    /// # NOTE
+    ///
+    /// * If the count of this WaitGroup is less than `count`, see transfer().
+    #[inline]
+    #[track_caller]
+    pub fn split_off(&self, count: usize) -> Self {
+        let wg = Self(self.0.create_like());
+        self.transfer(count, &wg);
+        wg
     }
 }
 
-pub struct WaitGroupGuard {
-    inner: Arc<WaitGroupInner>,
+/// Decrease `other` of transfer() back, when this WaitGroup underflows
+struct TransferUndo<'a, S: Storage> {
+    wg: &'a GenericWaitGroup<S>,
+    count: usize,
 }
 
-impl Drop for WaitGroupGuard {
+impl<S: Storage> Drop for TransferUndo<'_, S> {
+    #[inline]
     fn drop(&mut self) {
-        let inner = &self.inner;
-        inner.done(1);
+        self.wg.0.raw().done(self.count);
     }
 }
 
-struct WaitGroupInner {
-    left: AtomicI64,
-    waiting: AtomicI64,
-    waker: Mutex<Option<Waker>>,
-    waker_id: AtomicU64,
-}
-
-impl WaitGroupInner {
-    #[inline(always)]
-    fn new() -> Arc<Self> {
-        Arc::new(Self {
-            left: AtomicI64::new(0),
-            waiting: AtomicI64::new(-1),
-            waker: Mutex::new(None),
-            waker_id: AtomicU64::new(0),
-        })
+impl WaitGroup {
+    /// Leak the handle as a pointer-sized raw pointer, to be released by from_raw(), for
+    /// composite primitives storing the WaitGroup as a pointer.
+    ///
+    /// The handle is still counted as alive, see [DropPolicy].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// wg.add(1);
+    /// let ptr = wg.clone().into_raw();
+    /// let _wg = unsafe { WaitGroup::from_raw(ptr) };
+    /// _wg.done();
+    /// assert_eq!(wg.left(), 0);
+    /// ```
+    #[inline]
+    pub fn into_raw(self) -> *const c_void {
+        let wg = ManuallyDrop::new(self);
+        // Safety: the handle is never dropped, its Arc is moved out
+        let inner = unsafe { ptr::read(&wg.0) };
+        SharedArc::into_raw(inner) as *const c_void
     }
+
+    /// Reassemble a handle from into_raw().
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from [WaitGroup::into_raw()], and not be used again afterwards.
     #[inline]
-    fn done(&self, count: i64) {
-        let left = self.left.fetch_sub(count, Ordering::SeqCst) - count;
-        let waiting = self.waiting.load(Ordering::Acquire);
-        if left < 0 {
-            error!("WaitGroup.left {} < 0", left);
-            panic!("WaitGroup.left {} < 0", left);
-        }
-        if waiting < 0 {
-            return;
-        }
-        if left <= waiting {
-            // Do not take waker, it may be false waken when done() happened before newer wait()
-            if let Some(waker) = self.waker.lock().as_ref() {
-                waker.wake_by_ref();
-            }
-        }
+    pub unsafe fn from_raw(ptr: *const c_void) -> Self {
+        GenericWaitGroup(SharedArc::from_raw(ptr as *const WaitGroupInner))
     }
 
-    /// Once waker set, waker might be false waken many times
-    /// Returns: waker_id
+    /// Create a WaitGroup with non-default options.
     #[inline]
-    fn set_waker(&self, waker: Waker, target: usize) -> u64 {
-        let waker_id = self.waker_id.fetch_add(1, Ordering::SeqCst) + 1;
-        {
-            let mut guard = self.waker.lock();
-            guard.replace(waker);
-            let old_target = self.waiting.swap(target as i64, Ordering::SeqCst);
-            if old_target >= 0 {
-                panic!("Concurrent wait() by multiple coroutines is not supported")
-            }
-        }
-        waker_id
+    pub fn builder() -> WaitGroupBuilder {
+        WaitGroupBuilder::new()
     }
 
+    /// Add one to the WaitGroup, return a token to decrease the count when invoked or dropped.
+    ///
+    /// Intended for callback-based API which cannot hold a guard, see [DoneFn].
     #[inline]
-    fn cancel_wait(&self, waker_id: u64) {
-        let mut guard = self.waker.lock();
-        // In case wait() is canceled, eg. tokio timeout, do not disrupt other thread wait()
-        if self.waker_id.load(Ordering::Acquire) == waker_id {
-            self.waiting.store(-1, Ordering::Release);
-            let _ = guard.take();
-        }
+    pub fn wrap_fn(&self) -> DoneFn {
+        DoneFn::new(self.add_guard())
     }
-}
 
-struct WaitGroupFuture<'a> {
-    wg: &'a WaitGroupInner,
-    target: usize,
-    waker_id: u64,
-}
+    /// Add one to the WaitGroup, return a Waker which decreases the count on the first wake.
+    ///
+    /// Only the first wake (from any clone) counts. If all the clones are dropped without
+    /// being woken, the count is decreased as well, like dropping a guard.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let waker = wg.done_waker();
+    /// let _waker = waker.clone();
+    /// assert_eq!(wg.left(), 1);
+    /// waker.wake();
+    /// assert_eq!(wg.left(), 0);
+    /// _waker.wake();
+    /// assert_eq!(wg.left(), 0);
+    /// ```
+    #[inline]
+    pub fn done_waker(&self) -> Waker {
+        adapter::done_waker(self.add_guard())
+    }
 
-impl<'a> WaitGroupFuture<'a> {
-    #[inline(always)]
-    fn _poll(&mut self) -> bool {
-        let cur = self.wg.left.load(Ordering::Acquire);
-        if cur <= self.target as i64 {
-            self._clear();
-            true
-        } else {
-            false
-        }
+    /// List the WaitGroup under `name` in the [monitor], until the returned registration is
+    /// dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::{monitor, WaitGroup};
+    ///
+    /// let wg = WaitGroup::new();
+    /// let _registration = wg.register_monitor("requests");
+    /// let _guard = wg.add_guard();
+    /// let mut table = Vec::new();
+    /// monitor::render(&mut table).unwrap();
+    /// assert!(String::from_utf8(table).unwrap().contains("requests"));
+    /// ```
+    #[cfg(feature = "monitor")]
+    pub fn register_monitor(&self, name: impl Into<String>) -> MonitorRegistration {
+        monitor::register(self, name.into())
     }
 
-    #[inline(always)]
-    fn _clear(&mut self) {
-        if self.waker_id == 0 {
-            return;
-        }
-        self.wg.cancel_wait(self.waker_id);
-        self.waker_id = 0;
+    /// Drive `bar` from done(), eg. for a CLI printing the jobs remaining during a drain,
+    /// until the returned registration is dropped.
+    ///
+    /// The length of the bar is set to the count left, and extended by the count added since.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use indicatif::{ProgressBar, ProgressStyle};
+    ///
+    /// let wg = WaitGroup::new();
+    /// wg.add(3);
+    /// let bar = ProgressBar::hidden()
+    ///     .with_style(ProgressStyle::with_template("{pos}/{len} jobs done").unwrap());
+    /// let progress = wg.progress_bar(bar);
+    /// wg.done_many(3);
+    /// wg.wait_blocking();
+    /// progress.bar().finish();
+    /// assert_eq!(progress.bar().position(), 3);
+    /// ```
+    #[cfg(feature = "indicatif")]
+    pub fn progress_bar(&self, bar: indicatif::ProgressBar) -> ProgressBarRegistration {
+        progress::register(self, bar)
     }
-}
 
-/// When wait() is canceled with timeout(),  make sure it clear the waker.
-impl<'a> Drop for WaitGroupFuture<'a> {
-    fn drop(&mut self) {
-        self._clear();
+    /// Write to the eventfd each time the count drops to or below `threshold`, so that
+    /// epoll/mio based event loops can be woken.
+    ///
+    /// If the count is already at or below `threshold`, signal once immediately.
+    ///
+    /// The notification stops when the returned registration is dropped.
+    #[cfg(all(unix, feature = "eventfd"))]
+    pub fn register_eventfd<'fd>(
+        &self,
+        fd: std::os::fd::BorrowedFd<'fd>,
+        threshold: usize,
+    ) -> EventFdRegistration<'fd> {
+        eventfd::register(self, fd, threshold)
     }
-}
 
-impl<'a> Future for WaitGroupFuture<'a> {
-    type Output = ();
+    /// Signal the Windows event each time the count drops to or below `threshold`, so that
+    /// native components using WaitForMultipleObjects can observe this WaitGroup.
+    ///
+    /// If the count is already at or below `threshold`, signal once immediately.
+    ///
+    /// The notification stops when the returned registration is dropped.
+    #[cfg(all(windows, feature = "win-event"))]
+    pub fn register_event_handle<'h>(
+        &self,
+        event: std::os::windows::io::BorrowedHandle<'h>,
+        threshold: usize,
+    ) -> EventHandleRegistration<'h> {
+        win_event::register(self, event, threshold)
+    }
 
-    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
-        let _self = self.get_mut();
-        if _self.waker_id == 0 {
-            if _self._poll() {
-                return Poll::Ready(());
+    /// Wait until specified count is left in the WaitGroup, or the deadline is reached.
+    ///
+    /// Return Ok(false) means there's no waiting happened.
+    ///
+    /// Return Ok(true) means the blocking actually happened.
+    ///
+    /// Return Err(Elapsed) when the deadline is reached first.
+    ///
+    /// # NOTE
+    ///
+    /// * The deadline is expired by one timer thread shared by all the WaitGroups of the
+    ///   process, spawned by the first timed wait and never stopped, so no timer is allocated
+    ///   per call.
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use std::time::{Duration, Instant};
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    ///
+    /// rt.block_on(async move {
+    ///     let _guard = wg.add_guard();
+    ///     let deadline = Instant::now() + Duration::from_millis(10);
+    ///     assert!(wg.wait_to_deadline(0, deadline).await.is_err());
+    /// });
+    #[track_caller]
+    pub fn wait_to_deadline(
+        &self,
+        target: usize,
+        deadline: Instant,
+    ) -> impl Future<Output = Result<bool, Elapsed>> + '_ {
+        let caller = Location::caller();
+        async move {
+            let _self = self.0.as_ref();
+            let left = _self.load_left();
+            if left <= target as u64 && !_self.is_frozen() {
+                return Ok(false);
             }
-            _self.waker_id = _self.wg.set_waker(ctx.waker().clone(), _self.target);
-        }
-        if _self._poll() {
-            return Poll::Ready(());
+            WaitGroupDeadlineFuture {
+                f: WaitGroupFuture {
+                    wg: _self,
+                    target,
+                    waker_id: 0,
+                    caller,
+                },
+                deadline,
+                timer_id: 0,
+                recheck: false,
+            }
+            .await?;
+            Ok(true)
         }
-        Poll::Pending
     }
-}
-
-#[cfg(test)]
-mod tests {
-    extern crate rand;
-
-    use std::time::Duration;
-    use tokio::time::{sleep, timeout};
-
-    use super::*;
+
+    /// Wait until specified count is left in the WaitGroup, fail only when the WaitGroup makes
+    /// no progress (no done()) for `idle`, so that long drains still moving are not failures.
+    ///
+    /// Return Ok(false) means there's no waiting happened.
+    ///
+    /// Return Ok(true) means the blocking actually happened.
+    ///
+    /// # NOTE
+    ///
+    /// * Progress is checked at a quarter of `idle`, the error comes up to `idle / 4` late.
+    ///
+    /// * Same timer thread as [Self::wait_to_deadline()].
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    #[track_caller]
+    pub fn wait_to_with_progress_timeout(
+        &self,
+        target: usize,
+        idle: Duration,
+    ) -> impl Future<Output = Result<bool, Elapsed>> + '_ {
+        let caller = Location::caller();
+        let check = (idle / 4).max(Duration::from_millis(1));
+        async move {
+            let _self = self.0.as_ref();
+            if _self.load_left() <= target as u64 && !_self.is_frozen() {
+                return Ok(false);
+            }
+            let mut completed = self.completed();
+            let mut last_progress = Instant::now();
+            loop {
+                let idle_deadline = last_progress + idle;
+                let deadline = (Instant::now() + check).min(idle_deadline);
+                let r = WaitGroupDeadlineFuture {
+                    f: WaitGroupFuture {
+                        wg: _self,
+                        target,
+                        waker_id: 0,
+                        caller,
+                    },
+                    deadline,
+                    timer_id: 0,
+                    recheck: deadline < idle_deadline,
+                }
+                .await;
+                if r.is_ok() {
+                    return Ok(true);
+                }
+                let now = self.completed();
+                if now != completed {
+                    completed = now;
+                    last_progress = Instant::now();
+                } else if last_progress.elapsed() >= idle {
+                    return Err(Elapsed);
+                }
+            }
+        }
+    }
+
+    /// Wait until zero count in the WaitGroup, fail only when the WaitGroup makes no progress
+    /// for `idle`.
+    ///
+    /// See wait_to_with_progress_timeout().
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use std::time::Duration;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    ///
+    /// rt.block_on(async move {
+    ///     let _guard = wg.add_guard();
+    ///     let idle = Duration::from_millis(10);
+    ///     assert!(wg.wait_with_progress_timeout(idle).await.is_err());
+    /// });
+    #[inline(always)]
+    #[track_caller]
+    pub fn wait_with_progress_timeout(
+        &self,
+        idle: Duration,
+    ) -> impl Future<Output = Result<bool, Elapsed>> + '_ {
+        self.wait_to_with_progress_timeout(0, idle)
+    }
+
+    /// Block the current thread until zero count in the WaitGroup.
+    ///
+    /// Unlike wait(), any number of threads can block at the same time, and they are woken
+    /// together. Independent of the async waiting future.
+    ///
+    /// On wasm32 with the `wasm-atomics` feature, blocks with Atomics.wait, which is only
+    /// allowed in web workers.
+    #[inline]
+    #[track_caller]
+    pub fn wait_blocking(&self) {
+        self.wait_to_blocking(0);
+    }
+
+    /// Block the current thread until the count is less than or equal to `target`.
+    ///
+    /// Return true means the blocking actually happened.
+    ///
+    /// Any number of threads can block at the same time, with different targets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let wg = wg.clone();
+    ///         std::thread::spawn(move || wg.wait_blocking())
+    ///     })
+    ///     .collect();
+    /// wg.add(1);
+    /// wg.done();
+    /// for worker in workers {
+    ///     worker.join().unwrap();
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn wait_to_blocking(&self, target: usize) -> bool {
+        let inner = self.0.as_ref();
+        #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+        return wasm_wait::WasmWaiters::get(inner)
+            .wait(inner, target as u64, None)
+            .unwrap_or(true);
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm-atomics")))]
+        blocking::in_runtime(inner, Location::caller(), || {
+            BlockingWaiters::get(inner)
+                .wait(inner, target as u64, None)
+                .unwrap_or(true)
+        })
+    }
+
+    /// Block the current thread until zero count in the WaitGroup, or the timeout elapsed, for
+    /// sync shutdown paths giving up after a grace period.
+    ///
+    /// See wait_to_blocking_timeout().
+    #[inline]
+    #[track_caller]
+    pub fn wait_blocking_timeout(&self, timeout: Duration) -> Result<(), Elapsed> {
+        self.wait_to_blocking_timeout(0, timeout).map(|_| ())
+    }
+
+    /// Block the current thread until the count is less than or equal to `target`, or the
+    /// timeout elapsed.
+    ///
+    /// Return Ok(true) means the blocking actually happened.
+    #[track_caller]
+    pub fn wait_to_blocking_timeout(
+        &self,
+        target: usize,
+        timeout: Duration,
+    ) -> Result<bool, Elapsed> {
+        let inner = self.0.as_ref();
+        #[cfg(all(target_arch = "wasm32", feature = "wasm-atomics"))]
+        return wasm_wait::WasmWaiters::get(inner).wait(
+            inner,
+            target as u64,
+            Some(js_sys::Date::now() + timeout.as_secs_f64() * 1000.0),
+        );
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm-atomics")))]
+        blocking::in_runtime(inner, Location::caller(), || {
+            BlockingWaiters::get(inner).wait(inner, target as u64, Some(Instant::now() + timeout))
+        })
+    }
+
+    /// Spawn a thread named "waitgroup-callback", which blocks until the count is less than or
+    /// equal to `target`, then calls `callback`.
+    ///
+    /// For synchronous code which wants a completion notification without async.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let (tx, rx) = channel();
+    /// wg.add(1);
+    /// let th = wg.wait_callback_thread(0, move || tx.send(()).unwrap());
+    /// wg.done();
+    /// rx.recv().unwrap();
+    /// th.join().unwrap();
+    /// ```
+    pub fn wait_callback_thread<F>(&self, target: usize, callback: F) -> thread::JoinHandle<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let wg = self.clone();
+        thread::Builder::new()
+            .name("waitgroup-callback".to_string())
+            .spawn(move || {
+                wg.wait_to_blocking(target);
+                drop(wg);
+                callback();
+            })
+            .expect("spawn callback thread")
+    }
+
+    /// Decrease count by one, safe to call from interrupt handlers.
+    ///
+    /// Unlike done(), never blocks on the waker lock: if it is contended, the wake is left to
+    /// the lock holder. Nothing is allocated by this crate on this path, the waker itself is up
+    /// to the executor.
+    ///
+    /// # NOTE
+    ///
+    /// * Only the async waiter of wait() / wait_to() is woken here. The rest of the done() path
+    ///   is deferred until flush_isr(), or the next add() / done() from a normal context:
+    ///   wait_blocking() threads, listeners (eventfd / event handle, merge_from()), acquire()
+    ///   queue, wait_band() / wait_to_eq() waiters, drain watches, the observer and the stats
+    ///   gauge.
+    ///
+    /// * Underflow is handled by the [ErrorPolicy] as done().
+    #[inline]
+    #[track_caller]
+    pub fn done_from_isr(&self) {
+        self.0.done_from_isr(1);
+    }
+
+    /// Run the done() path deferred by done_from_isr(), eg. from the bottom half of the
+    /// interrupt handler. Not safe to call from interrupt handlers.
+    ///
+    /// The deferred units are reported at once, against the count at the time of flush.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// wg.add(1);
+    /// let _wg = wg.clone();
+    /// let th = std::thread::spawn(move || _wg.wait_blocking());
+    /// // In the interrupt handler
+    /// wg.done_from_isr();
+    /// // Later in the normal context
+    /// wg.flush_isr();
+    /// th.join().unwrap();
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn flush_isr(&self) {
+        self.0.flush_isr();
+    }
+
+    /// Wait until zero count in the WaitGroup, then report whether any guard was dropped by a
+    /// panicking thread.
+    ///
+    /// Always Ok(()) unless enabled with [WaitGroupBuilder::poison_on_panic()].
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported.
+    #[track_caller]
+    pub fn wait_result(&self) -> impl Future<Output = Result<(), Poisoned>> + '_ {
+        let f = self.wait_to(0);
+        async move {
+            f.await;
+            match self.0.poisoned.load(Ordering::Acquire) {
+                0 => Ok(()),
+                count => Err(Poisoned { count }),
+            }
+        }
+    }
+
+    /// Defer waking the waiters, even when their condition becomes true, until unfreeze().
+    ///
+    /// For coordinated checkpoints, where the controller completes the conditions of several
+    /// groups before releasing any waiter. The count still changes while frozen.
+    ///
+    /// Applies to wait futures and blocking waits, not to eventfd / event handle notifications.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let (wg1, wg2) = (WaitGroup::new(), WaitGroup::new());
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let (guard1, guard2) = (wg1.add_guard(), wg2.add_guard());
+    ///     wg1.freeze();
+    ///     wg2.freeze();
+    ///     drop(guard1);
+    ///     drop(guard2);
+    ///     // Both conditions are true, release the waiters together
+    ///     wg1.unfreeze();
+    ///     wg2.unfreeze();
+    ///     wg1.wait().await;
+    ///     wg2.wait().await;
+    /// });
+    /// ```
+    #[inline]
+    pub fn freeze(&self) {
+        self.0.frozen.store(true, Ordering::SeqCst);
+    }
+
+    /// Release the waiters held by freeze() whose condition is true.
+    #[inline]
+    pub fn unfreeze(&self) {
+        self.0.unfreeze();
+    }
+
+    /// Return true between freeze() and unfreeze().
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        self.0.frozen.load(Ordering::SeqCst)
+    }
+
+    /// Wake the waiting future, the blocked threads, the queued acquirers and the waits of
+    /// wait_above() and its siblings to re-check their condition, without changing the count,
+    /// eg. after an out-of-band state change.
+    ///
+    /// A waiter whose condition is still false goes back to waiting. The closure of
+    /// wait_while() is evaluated again, for a condition over external state.
+    #[inline]
+    pub fn notify_waiters(&self) {
+        self.0.notify_waiters();
+    }
+
+    /// Wait until the count is at or below `target`, and close the WaitGroup against add() in
+    /// the same step, return the count left at the claim.
+    ///
+    /// From then on the count only goes down, a late add() racing with the claim is either
+    /// seen by it, or undone and rejected.
+    ///
+    /// # NOTE
+    ///
+    /// * add() after closed is handled by the [ErrorPolicy], and does not change the count,
+    ///   producers racing with the claim should use try_add_guard().
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported, the WaitGroup stays open.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::{ErrorPolicy, WaitGroup};
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let guard = wg.add_guard();
+    ///     tokio::spawn(async move {
+    ///         drop(guard);
+    ///     });
+    ///     assert_eq!(wg.claim_at(0).await, 0);
+    ///     assert!(wg.is_closed());
+    ///     assert!(wg.try_add_guard().is_none());
+    ///     // Rejected
+    ///     wg.add(1);
+    ///     assert_eq!(wg.left(), 0);
+    /// });
+    /// ```
+    #[track_caller]
+    pub fn claim_at(&self, target: usize) -> impl Future<Output = usize> + '_ {
+        let f = self.wait_to(target);
+        async move {
+            f.await;
+            loop {
+                if self.0.try_close(target as u64) {
+                    return self.left();
+                }
+                // A late add() won the race
+                self.wait_to(target).await;
+            }
+        }
+    }
+
+    /// Set the count back to zero and wake the waiters, eg. to reuse the WaitGroup after an
+    /// error path abandoned outstanding guards.
+    ///
+    /// The waiting future resolves with the zero count. Starts a new generation if
+    /// add_generation_guard() was used, so that the abandoned generation guards are ignored
+    /// when dropped. A plain guard dropped after the reset decreases the count of the next
+    /// cycle, or reports [CountError::Underflow] at zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let abandoned = wg.add_generation_guard().unwrap();
+    /// wg.add(2);
+    /// wg.reset();
+    /// assert_eq!(wg.left(), 0);
+    /// drop(abandoned);
+    /// assert_eq!(wg.left(), 0);
+    /// ```
+    #[inline]
+    pub fn reset(&self) {
+        self.0.reset();
+    }
+
+    /// The current generation of the guards from add_generation_guard(), starting at 0.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.0.generations.get().map_or(0, Generations::current)
+    }
+
+    /// Add one to the WaitGroup, return a guard bound to the current generation, for reusing
+    /// the WaitGroup across cycles. Return None if it is closed by claim_at(), same as
+    /// try_add_guard().
+    ///
+    /// Dropping it decreases the count only while its generation is current, a late guard
+    /// from a previous cycle does not corrupt the count of the next one. The guards of a
+    /// generation take a lock on creation and drop, plain add() and done() are not bound to
+    /// any generation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let stuck = wg.add_generation_guard().unwrap();
+    /// // Give up on the cycle
+    /// assert_eq!(wg.next_generation(), 1);
+    /// assert_eq!(wg.left(), 0);
+    /// let guard = wg.add_generation_guard().unwrap();
+    /// drop(stuck);
+    /// assert_eq!(wg.left(), 1);
+    /// drop(guard);
+    /// assert_eq!(wg.left(), 0);
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn add_generation_guard(&self) -> Option<GenerationGuard> {
+        match GenerationGuard::new(self.0.clone()) {
+            Ok(guard) => Some(guard),
+            Err(CountError::Closed { .. }) => None,
+            Err(err) => {
+                self.0.report(err);
+                None
+            }
+        }
+    }
+
+    /// Start a new generation, return it. The count of the guards of the previous one still
+    /// outstanding is taken off, as if they were dropped, and their drop is ignored.
+    #[inline]
+    #[track_caller]
+    pub fn next_generation(&self) -> u64 {
+        Generations::get(&self.0).advance(&self.0)
+    }
+
+    /// Wait until the guards of `generation` are all dropped, or abandoned by
+    /// next_generation(). A past generation resolves at once.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    pub fn wait_generation(&self, generation: u64) -> impl Future<Output = ()> + '_ {
+        GenerationFuture::new(Generations::get(&self.0), generation)
+    }
+
+    /// Wait until at least `target` is left in the WaitGroup, eg. until enough workers have
+    /// registered, woken by add().
+    ///
+    /// Return false means there's no waiting happened.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let _wg = wg.clone();
+    ///     let th = tokio::spawn(async move {
+    ///         (0..3).map(|_| _wg.add_guard()).collect::<Vec<_>>()
+    ///     });
+    ///     wg.wait_above(3).await;
+    ///     drop(th.await.unwrap());
+    /// });
+    /// ```
+    pub async fn wait_above(&self, target: usize) -> bool {
+        let f = BandFuture::new(&self.0, 0, target as u64);
+        if f.check().is_some() {
+            return false;
+        }
+        f.await;
+        true
+    }
+
+    /// Wait until exactly `target` is left in the WaitGroup, eg. for the last survivor.
+    ///
+    /// Woken by add() and done() once the count lands on or crosses `target`. A count passing
+    /// through `target` between two polls keeps waiting, until the count lands on it again.
+    ///
+    /// Return false means there's no waiting happened.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    pub async fn wait_to_eq(&self, target: usize) -> bool {
+        let target = target as u64;
+        let mut left = self.0.left.load(Ordering::SeqCst);
+        if left == target {
+            return false;
+        }
+        while left != target {
+            // Resolve once the count is back on the other side of target, or on it
+            left = if left > target {
+                BandFuture::new(&self.0, target + 1, u64::MAX).await
+            } else {
+                BandFuture::new(&self.0, 0, target).await
+            };
+        }
+        true
+    }
+
+    /// Snapshot the count, return a future resolving with the new count once it differs, in
+    /// either direction, for reacting to activity without polling.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let changed = wg.wait_changed();
+    ///     wg.add(1);
+    ///     assert_eq!(changed.await, 1);
+    ///     let changed = wg.wait_changed();
+    ///     wg.done();
+    ///     assert_eq!(changed.await, 0);
+    /// });
+    /// ```
+    pub fn wait_changed(&self) -> impl Future<Output = usize> + '_ {
+        let left = self.0.left.load(Ordering::SeqCst);
+        let f = BandFuture::new(&self.0, left, left + 1);
+        async move { f.await as usize }
+    }
+
+    /// Wait while `condition` returns true for the count, return the count it returned false
+    /// for, like [std::sync::Condvar::wait_while()].
+    ///
+    /// The closure is re-evaluated on every add() and done() that wakes the future, and on
+    /// notify_waiters(). A count passed through between two polls may not be seen.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     wg.add(9);
+    ///     let _wg = wg.clone();
+    ///     let th = tokio::spawn(async move {
+    ///         // Until even and below 8
+    ///         _wg.wait_while(|left| left % 2 == 1 || left >= 8).await
+    ///     });
+    ///     wg.done_many(3);
+    ///     assert_eq!(th.await.unwrap(), 6);
+    /// });
+    /// ```
+    pub async fn wait_while<F: FnMut(usize) -> bool>(&self, mut condition: F) -> usize {
+        loop {
+            let left = self.0.left.load(Ordering::SeqCst);
+            if !condition(left as usize) {
+                return left as usize;
+            }
+            BandFuture::new(&self.0, left, left + 1)
+                .interruptible()
+                .await;
+        }
+    }
+
+    /// Wait until the count is within `range`, return the count observed in it.
+    ///
+    /// Woken by done() from above the range and by add() from below it. Any number of waits
+    /// is allowed, besides the one of wait_to(). Canceling future is supported.
+    ///
+    /// # Panics
+    ///
+    /// If the range is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     wg.add(10);
+    ///     let _wg = wg.clone();
+    ///     let th = tokio::spawn(async move { _wg.wait_in(2..=5).await });
+    ///     wg.done_many(6);
+    ///     assert_eq!(th.await.unwrap(), 4);
+    /// });
+    /// ```
+    #[track_caller]
+    pub fn wait_in(&self, range: RangeInclusive<usize>) -> impl Future<Output = usize> + '_ {
+        assert!(
+            !range.is_empty(),
+            "wait_in() with an empty range {:?}",
+            range
+        );
+        let (low, high) = (*range.start() as u64, *range.end() as u64);
+        async move {
+            loop {
+                let left = self.0.left.load(Ordering::SeqCst);
+                if (low..=high).contains(&left) {
+                    return left as usize;
+                }
+                if left > high {
+                    BandFuture::new(&self.0, high + 1, u64::MAX).await;
+                } else {
+                    BandFuture::new(&self.0, 0, low).await;
+                }
+            }
+        }
+    }
+
+    /// Snapshot the count, wait until it crosses one of `thresholds`, return the index of the
+    /// first one crossed, eg. for tiered backpressure levels.
+    ///
+    /// A threshold below the snapshot is crossed once the count is at or below it, one above
+    /// once the count is at or above it, one equal to it at once. When a single change crosses
+    /// several, the one nearest to the snapshot is returned.
+    ///
+    /// Any number of waits is allowed, besides the one of wait_to(). Canceling future is
+    /// supported.
+    ///
+    /// # Panics
+    ///
+    /// If `thresholds` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     wg.add(5);
+    ///     let level = wg.wait_any_of(&[0, 2, 8]);
+    ///     wg.done_many(4);
+    ///     assert_eq!(level.await, 1);
+    ///     wg.done();
+    /// });
+    /// ```
+    #[track_caller]
+    pub fn wait_any_of(&self, thresholds: &[usize]) -> impl Future<Output = usize> + '_ {
+        assert!(!thresholds.is_empty(), "wait_any_of() without thresholds");
+        let left = self.0.left.load(Ordering::SeqCst) as usize;
+        let position = |t: usize| thresholds.iter().position(|x| *x == t);
+        let equal = position(left);
+        let below = thresholds.iter().copied().filter(|t| *t < left).max();
+        let above = thresholds.iter().copied().filter(|t| *t > left).min();
+        let (below_idx, above_idx) = (below.and_then(position), above.and_then(position));
+        let floor = below.map_or(0, |t| t as u64 + 1);
+        let ceil = above.map_or(u64::MAX, |t| t as u64);
+        async move {
+            if let Some(idx) = equal {
+                return idx;
+            }
+            let left = BandFuture::new(&self.0, floor, ceil).await;
+            if left < floor {
+                below_idx.unwrap()
+            } else {
+                above_idx.unwrap()
+            }
+        }
+    }
+
+    /// Add one to the WaitGroup with a guard if the count stays within `limit`, otherwise None,
+    /// for admission control that never waits.
+    ///
+    /// The count is checked and increased in one CAS, concurrent callers never exceed `limit`.
+    /// None as well if the WaitGroup is closed by claim_at().
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let first = wg.try_acquire(2).unwrap();
+    /// let _second = wg.try_acquire(2).unwrap();
+    /// assert!(wg.try_acquire(2).is_none());
+    /// drop(first);
+    /// assert!(wg.try_acquire(2).is_some());
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn try_acquire(&self, limit: usize) -> Option<WaitGroupGuard> {
+        if limit == 0 {
+            return None;
+        }
+        match self.0.increase_from(1, limit as u64 - 1) {
+            Ok(true) => Some(GenericWaitGroupGuard::new(self.0.clone())),
+            Ok(false) | Err(CountError::Closed { .. }) => None,
+            Err(err) => {
+                self.0.report(err);
+                None
+            }
+        }
+    }
+
+    /// Add as many of `n` as fit with the count at or below `max`, return how many were
+    /// added, for admitting a partial batch instead of all or nothing.
+    ///
+    /// The count is checked and increased in one CAS. Zero if the WaitGroup is closed by
+    /// claim_at().
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// assert_eq!(wg.add_many_capped(3, 5), 3);
+    /// assert_eq!(wg.add_many_capped(3, 5), 2);
+    /// assert_eq!(wg.add_many_capped(3, 5), 0);
+    /// wg.done_many(5);
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn add_many_capped(&self, n: usize, max: usize) -> usize {
+        let added = self.0.increase_with(n, |old| {
+            let room = (max as u64).saturating_sub(old).min(n as u64);
+            (room > 0).then_some(room as usize)
+        });
+        match added {
+            Ok(added) => added.unwrap_or(0),
+            Err(CountError::Closed { .. }) => 0,
+            Err(err) => {
+                self.0.report(err);
+                0
+            }
+        }
+    }
+
+    /// Add one to the WaitGroup with a guard once the count stays within `limit`, or None if it
+    /// is closed by claim_at().
+    ///
+    /// Any number of tasks may wait in acquire(), they are admitted in arrival order.
+    ///
+    /// # NOTE
+    ///
+    /// * Canceling future is supported, the capacity granted to a canceled future is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     for _ in 0..10 {
+    ///         let _wg = wg.clone();
+    ///         tokio::spawn(async move {
+    ///             // At most 4 at the same time
+    ///             let _guard = _wg.acquire(4).await.unwrap();
+    ///             // Do something
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    #[inline]
+    pub fn acquire(&self, limit: usize) -> impl Future<Output = Option<WaitGroupGuard>> + '_ {
+        let f = AdmissionFuture::new(self.0.as_ref(), 1, limit);
+        async move { f.await.then(|| GenericWaitGroupGuard::new(self.0.clone())) }
+    }
+
+    /// Add `weight` to the WaitGroup once the count stays within `limit`, for work of different
+    /// costs sharing a budget, eg. bytes in flight. Return None if closed by claim_at().
+    ///
+    /// Queued with acquire() in arrival order. A weight larger than `limit` is admitted alone,
+    /// when the count is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     // Up to 1MB buffered
+    ///     let guard = wg.acquire_weighted(512 * 1024, 1024 * 1024).await.unwrap();
+    ///     assert_eq!(guard.weight(), 512 * 1024);
+    ///     let _wg = wg.clone();
+    ///     let th = tokio::spawn(async move { _wg.acquire_weighted(768 * 1024, 1024 * 1024).await });
+    ///     drop(guard);
+    ///     assert_eq!(th.await.unwrap().unwrap().weight(), 768 * 1024);
+    /// });
+    /// ```
+    #[inline]
+    pub fn acquire_weighted(
+        &self,
+        weight: usize,
+        limit: usize,
+    ) -> impl Future<Output = Option<WeightedGuard>> + '_ {
+        let f = AdmissionFuture::new(self.0.as_ref(), weight, limit);
+        async move { f.await.then(|| WeightedGuard::new(self.0.clone(), weight)) }
+    }
+
+    /// Wait until the count is at or below `target`, then add `count` in the same step, so that
+    /// concurrent batch producers never overshoot `target + count`.
+    ///
+    /// Return a guard decreasing `count` on drop, or None if it is closed by claim_at(), same as
+    /// try_add_guard().
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// * Canceling future is supported, nothing is added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     for chunk in [8, 4, 16] {
+    ///         // Admit the chunk while at most 8 items are in flight
+    ///         let guard = wg.wait_to_and_add(8, chunk).await.unwrap();
+    ///         tokio::spawn(async move {
+    ///             // Process the chunk
+    ///             drop(guard);
+    ///         });
+    ///     }
+    ///     wg.wait().await;
+    /// });
+    /// ```
+    #[track_caller]
+    pub fn wait_to_and_add(
+        &self,
+        target: usize,
+        count: usize,
+    ) -> impl Future<Output = Option<WeightedGuard>> + '_ {
+        let caller = Location::caller();
+        async move {
+            loop {
+                match self.0.increase_from(count, target as u64) {
+                    Ok(true) => return Some(WeightedGuard::new(self.0.clone(), count)),
+                    Ok(false) => {}
+                    Err(CountError::Closed { .. }) => return None,
+                    Err(err) => {
+                        self.0.report(err);
+                        return None;
+                    }
+                }
+                WaitGroupFuture {
+                    wg: self.0.as_ref(),
+                    target,
+                    waker_id: 0,
+                    caller,
+                }
+                .await;
+            }
+        }
+    }
+
+    /// The target of the waiting future, None if not waiting, eg. to hold back an add() that
+    /// would conflict with a drain in progress.
+    ///
+    /// A waiter woken on every done(), like wait_to_with_progress(), is reported as usize::MAX.
+    /// Blocking waits are not included.
+    #[inline]
+    pub fn is_waiting(&self) -> Option<usize> {
+        match self.0.waiting.load(Ordering::Acquire) {
+            NO_WAITER => None,
+            WAKE_ALWAYS => Some(usize::MAX),
+            target => Some(target as usize),
+        }
+    }
+
+    /// Return true after close() or claim_at() succeeded.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    /// Close the WaitGroup against add() at its current count, like claim_at() without
+    /// waiting.
+    #[inline]
+    pub fn close(&self) {
+        self.0.try_close(u64::MAX);
+    }
+
+    /// The phase of the shutdown, Open until closed, Drained when the count is zero after.
+    #[inline]
+    pub fn state(&self) -> DrainState {
+        DrainState::of(&self.0)
+    }
+
+    /// Watch the changes of state(), to react to each phase of the shutdown.
+    ///
+    /// See [DrainWatch].
+    #[inline]
+    pub fn watch_state(&self) -> DrainWatch {
+        DrainWatch::new(self.0.clone())
+    }
+
+    /// Resolve once the WaitGroup is closed by close() or claim_at(), so that long-lived
+    /// workers wind down when the drain starts, instead of being aborted at its deadline.
+    ///
+    /// Not a waiting future, any number of them is allowed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let guard = wg.add_guard();
+    ///     let closed = wg.closed();
+    ///     tokio::spawn(async move {
+    ///         // Serve until the drain starts
+    ///         closed.await;
+    ///         drop(guard);
+    ///     });
+    ///     wg.close();
+    ///     wg.wait().await;
+    /// });
+    /// ```
+    pub fn closed(&self) -> impl Future<Output = ()> + 'static {
+        let mut watch = self.watch_state();
+        async move {
+            while watch.last() == DrainState::Open {
+                watch.changed().await;
+            }
+        }
+    }
+
+    /// Resolve once the WaitGroup is closed and its count is zero, like the wait() of tokio's
+    /// TaskTracker, for the graceful shutdown without an external flag.
+    ///
+    /// Unlike wait(), a zero count does not resolve it before close(), and closing at zero
+    /// resolves it at once. Not a waiting future, any number of them is allowed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let drained = wg.drained();
+    ///     let guard = wg.add_guard();
+    ///     tokio::spawn(async move {
+    ///         // Do something
+    ///         drop(guard);
+    ///     });
+    ///     wg.close();
+    ///     assert!(wg.try_add_guard().is_none());
+    ///     drained.await;
+    /// });
+    /// ```
+    pub fn drained(&self) -> impl Future<Output = ()> + 'static {
+        let mut watch = self.watch_state();
+        async move {
+            while watch.last() != DrainState::Drained {
+                watch.changed().await;
+            }
+        }
+    }
+
+    /// Add one to the WaitGroup with a guard, or None if it is closed by claim_at().
+    ///
+    /// For producers racing with the claim, the guard of a rejected add_guard() would
+    /// decrease a count it never added.
+    #[inline]
+    #[track_caller]
+    pub fn try_add_guard(&self) -> Option<WaitGroupGuard> {
+        match self.0.increase(1) {
+            Ok(()) => Some(GenericWaitGroupGuard::new(self.0.clone())),
+            Err(CountError::Closed { .. }) => None,
+            Err(err) => {
+                self.0.report(err);
+                None
+            }
+        }
+    }
+
+    /// Return true if any guard was dropped by a panicking thread, with poison mode enabled.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.0.poisoned.load(Ordering::Acquire) > 0
+    }
+
+    /// Clear the poisoned state, to reuse the WaitGroup after handling the failure.
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.0.poisoned.store(0, Ordering::Release);
+    }
+
+    /// Start a watchdog thread, which logs a warning with the state of this WaitGroup
+    /// when the count stays nonzero without any done() for longer than `bound`.
+    ///
+    /// Reported once per stall. The watchdog stops when the returned handle is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use std::time::Duration;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let _watchdog = wg.watchdog(Duration::from_secs(30));
+    /// ```
+    pub fn watchdog(&self, bound: Duration) -> Watchdog {
+        self.watchdog_with(bound, move |wg| watchdog::log_stall(wg, bound))
+    }
+
+    /// Like watchdog(), but call `on_stall` instead of logging.
+    pub fn watchdog_with<F>(&self, bound: Duration, on_stall: F) -> Watchdog
+    where
+        F: FnMut(&WaitGroup) + Send + 'static,
+    {
+        Watchdog::spawn(self.clone(), bound, on_stall)
+    }
+
+    /// Count the tasks of a TaskTracker as one unit, until the tracker is closed and empty,
+    /// so that wait() covers the code not migrated off the TaskTracker yet.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a tokio runtime, the tracker is awaited by a task spawned here.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    /// use tokio_util::task::TaskTracker;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let tracker = TaskTracker::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let _handle = wg.attach_task_tracker(&tracker);
+    ///     tracker.spawn(async {
+    ///         // Do something
+    ///     });
+    ///     tracker.close();
+    ///     wg.wait().await;
+    ///     assert!(tracker.is_empty());
+    /// });
+    /// ```
+    #[cfg(feature = "tokio-util")]
+    #[track_caller]
+    pub fn attach_task_tracker(
+        &self,
+        tracker: &tokio_util::task::TaskTracker,
+    ) -> TrackedJoinHandle<()> {
+        let tracker = tracker.clone();
+        self.attach_to(tokio::spawn(async move { tracker.wait().await }))
+    }
+
+    /// Add one to both the WaitGroup and `tracker`, so that TaskTracker::wait() covers the
+    /// code already migrated to the WaitGroup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio_util::task::TaskTracker;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let tracker = TaskTracker::new();
+    /// let guard = wg.add_guard_with_tracker(&tracker);
+    /// assert_eq!((wg.left(), tracker.len()), (1, 1));
+    /// drop(guard);
+    /// assert_eq!((wg.left(), tracker.len()), (0, 0));
+    /// ```
+    #[cfg(feature = "tokio-util")]
+    #[inline]
+    #[track_caller]
+    pub fn add_guard_with_tracker(&self, tracker: &tokio_util::task::TaskTracker) -> TrackerGuard {
+        TrackerGuard::new(self.add_guard(), tracker)
+    }
+
+    /// Track a tokio task already spawned, eg. by a framework, until it completes.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a tokio runtime, the task is awaited by another task spawned here.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    /// rt.block_on(async move {
+    ///     let handle = tokio::spawn(async { 1 });
+    ///     let handle = wg.attach_to(handle);
+    ///     wg.wait().await;
+    ///     assert_eq!(handle.await.unwrap(), 1);
+    /// });
+    /// ```
+    #[cfg(feature = "tokio")]
+    #[track_caller]
+    pub fn attach_to<T: Send + 'static>(
+        &self,
+        handle: tokio::task::JoinHandle<T>,
+    ) -> TrackedJoinHandle<T> {
+        TrackedJoinHandle::new(handle, self.add_guard())
+    }
+
+    /// Build an escalation policy for the drain, with a callback at each stage.
+    ///
+    /// See [ShutdownPlan].
+    #[inline]
+    pub fn shutdown_plan(&self) -> ShutdownPlan<'_> {
+        ShutdownPlan::new(self)
+    }
+
+    /// Absorb the outstanding count of `other`, which keeps being decreased by its guards and
+    /// clones, and forwards the decrease to this WaitGroup.
+    ///
+    /// Only the count at the time of merge is forwarded, count added to `other` afterwards is
+    /// not tracked by this WaitGroup.
+    ///
+    /// # NOTE
+    ///
+    /// * `other` is borrowed, the handle is dropped by the caller, through its [DropPolicy] as
+    ///   any other drop. Its guards keep forwarding after it is gone.
+    ///
+    /// * done_from_isr() on `other` is forwarded only once flushed, see flush_isr(). Until
+    ///   then, units completed that way keep this WaitGroup waiting.
+    pub fn merge_from(&self, other: &WaitGroup) {
+        merge::merge(&self.0, &other.0);
+    }
+
+    /// Total count decreased since creation.
+    #[inline]
+    pub fn completed(&self) -> u64 {
+        self.0.completed.load(Ordering::Relaxed)
+    }
+
+    /// Take the totals of add() and done() at this moment, for periodic telemetry.
+    #[inline]
+    pub fn snapshot(&self) -> CountSnapshot {
+        CountSnapshot {
+            added: self.0.added.load(Ordering::Relaxed),
+            completed: self.0.completed.load(Ordering::Relaxed),
+            at: Instant::now(),
+        }
+    }
+
+    /// Totals of add() and done() since the snapshot was taken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let snapshot = wg.snapshot();
+    /// wg.add(3);
+    /// wg.done();
+    /// let delta = wg.delta_since(&snapshot);
+    /// assert_eq!((delta.added, delta.completed), (3, 1));
+    /// ```
+    #[inline]
+    pub fn delta_since(&self, snapshot: &CountSnapshot) -> CountDelta {
+        self.snapshot().delta_since(snapshot)
+    }
+
+    /// Statistics with the moving average of the count, a smoothed load signal.
+    #[cfg(feature = "stats")]
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        Stats {
+            left: self.0.left.load(Ordering::Acquire) as usize,
+            outstanding_avg: self.0.gauge.get(),
+            added: self.0.added.load(Ordering::Relaxed),
+            completed: self.0.completed.load(Ordering::Relaxed),
+            waits_completed: self.0.waits_completed.load(Ordering::Relaxed),
+            waits_canceled: self.0.waits_canceled.load(Ordering::Relaxed),
+            done_contended: self.0.done_contended.load(Ordering::Relaxed),
+            register_contended: self.0.register_contended.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Add one to the WaitGroup, with a guard registered with the name of the task and an
+    /// opaque `user_data`, listed by outstanding() until dropped.
+    #[cfg(feature = "diagnostics")]
+    #[inline]
+    #[track_caller]
+    pub fn add_guard_with_info(&self, name: &str, user_data: u64) -> TaggedGuard {
+        registry::add_guard(self, Some(name.to_string()), user_data, None)
+    }
+
+    /// Add one to the WaitGroup, with a guard registered with the time it is expected to be
+    /// dropped, `dur` from now.
+    ///
+    /// See overdue().
+    #[cfg(feature = "diagnostics")]
+    #[inline]
+    #[track_caller]
+    pub fn add_guard_with_deadline(&self, dur: Duration) -> TaggedGuard {
+        registry::add_guard(self, None, 0, Some(dur))
+    }
+
+    /// Registered guards not dropped yet, ordered by creation. Guards from add_guard() and
+    /// add() are not registered.
+    #[cfg(feature = "diagnostics")]
+    pub fn outstanding(&self) -> Vec<GuardInfo> {
+        self.0.registry.collect(|_| true)
+    }
+
+    /// Guards from add_guard_with_deadline() not dropped by their deadline, for operators to
+    /// see which tasks blew their budget during a slow drain.
+    #[cfg(feature = "diagnostics")]
+    pub fn overdue(&self) -> Vec<GuardInfo> {
+        let now = Instant::now();
+        self.0
+            .registry
+            .collect(|info| info.deadline.is_some_and(|deadline| deadline <= now))
+    }
+
+    /// How long the oldest registered guard has been outstanding, None if there is none.
+    ///
+    /// For alerting on a unit of work in flight for too long, independent of the count. Guards
+    /// from add_guard() and add() are not registered.
+    #[cfg(feature = "diagnostics")]
+    #[inline]
+    pub fn oldest_outstanding_age(&self) -> Option<Duration> {
+        self.0.registry.oldest().map(|added_at| added_at.elapsed())
+    }
+
+    /// Readiness for a probe handler, failing once a wait is in progress.
+    ///
+    /// See [Readiness].
+    #[cfg(feature = "health")]
+    #[inline]
+    pub fn readiness(&self) -> Readiness {
+        health::readiness(self)
+    }
+
+    /// Send the count to a statsd endpoint, until the returned registration is dropped.
+    ///
+    /// See [StatsdExporter].
+    #[cfg(feature = "statsd")]
+    pub fn export_statsd(&self, exporter: StatsdExporter) -> StatsdRegistration {
+        statsd::export(self, exporter)
+    }
+
+    /// Wait until zero count in the WaitGroup, panic with a dump of the state if it does not
+    /// settle within `dur`.
+    ///
+    /// # NOTE
+    ///
+    /// * Only assume one waiting future at the same time, otherwise will panic.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    /// use std::time::Duration;
+    /// use tokio::runtime::Runtime;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let rt = Runtime::new().unwrap();
+    ///
+    /// rt.block_on(async move {
+    ///     let _guard = wg.add_guard();
+    ///     tokio::spawn(async move {
+    ///         drop(_guard);
+    ///     });
+    ///     wg.assert_settles_within(Duration::from_secs(1)).await;
+    /// });
+    /// ```
+    #[cfg(feature = "test-util")]
+    #[track_caller]
+    pub fn assert_settles_within(&self, dur: Duration) -> impl Future<Output = ()> + '_ {
+        let caller = std::panic::Location::caller();
+        async move {
+            if self
+                .wait_to_deadline(0, Instant::now() + dur)
+                .await
+                .is_err()
+            {
+                panic!("{:?} not settled within {:?}, at {}", self, dur, caller);
+            }
+        }
+    }
+
+    /// Id of the latest waiting future, 0 if never waited.
+    #[cfg(feature = "test-util")]
+    #[inline]
+    pub fn waker_id(&self) -> u64 {
+        self.0.waker_id.load(Ordering::Acquire)
+    }
+
+    /// The count the waiting future is woken at, None if not waiting.
+    #[cfg(feature = "test-util")]
+    #[inline]
+    pub fn waiting_target(&self) -> Option<usize> {
+        let waiting = self.0.waiting.load(Ordering::Acquire);
+        (waiting != NO_WAITER).then_some(waiting as usize)
+    }
+
+    /// Return true if a waker is registered by a waiting future.
+    #[cfg(feature = "test-util")]
+    #[inline]
+    pub fn has_waker(&self) -> bool {
+        self.0.with_waker(|guard| guard.is_some())
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.as_ref();
+        let waiting = inner.waiting.load(Ordering::Acquire);
+        f.debug_struct("WaitGroup")
+            .field("left", &inner.left.load(Ordering::Acquire))
+            .field("waiting", &(waiting != NO_WAITER).then_some(waiting))
+            .field("waker_id", &inner.waker_id.load(Ordering::Acquire))
+            .field("completed", &inner.completed.load(Ordering::Relaxed))
+            .field("poisoned", &inner.poisoned.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Decrease the count of its WaitGroup on drop, use the [WaitGroupGuard] or
+/// [LocalWaitGroupGuard] alias.
+///
+/// The guard can be released early and rearmed, so that a long-lived worker marks itself busy
+/// and idle in a loop with the same guard.
+pub struct GenericWaitGroupGuard<S: Storage> {
+    inner: S,
+    /// Holding one count, false after release()
+    armed: bool,
+}
+
+impl<S: Storage> GenericWaitGroupGuard<S> {
+    #[inline(always)]
+    pub(crate) fn new(inner: S) -> Self {
+        Self { inner, armed: true }
+    }
+
+    /// Released if the add was rejected
+    #[inline(always)]
+    pub(crate) fn with_armed(inner: S, armed: bool) -> Self {
+        Self { inner, armed }
+    }
+
+    /// Decrease the count now instead of on drop, no-op if already released.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let wg = WaitGroup::new();
+    /// let mut guard = wg.add_guard();
+    /// for _job in 0..3 {
+    ///     guard.rearm();
+    ///     // Busy
+    ///     assert_eq!(wg.left(), 1);
+    ///     guard.release();
+    ///     // Idle
+    ///     assert_eq!(wg.left(), 0);
+    /// }
+    /// ```
+    #[inline]
+    pub fn release(&mut self) {
+        if self.armed {
+            self.armed = false;
+            self.inner.raw().guard_done();
+        }
+    }
+
+    /// Add one to the WaitGroup again after release(), no-op if not released. Stays released
+    /// if the add is rejected, eg. closed.
+    #[inline]
+    #[track_caller]
+    pub fn rearm(&mut self) {
+        if !self.armed {
+            self.armed = self.inner.raw().add(1);
+        }
+    }
+
+    /// Return true if the guard holds its count.
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Move the count of the guard to `to`, return the guard of `to`, for an item passing
+    /// between stages.
+    ///
+    /// `to` is increased before this WaitGroup is decreased, like
+    /// [GenericWaitGroup::transfer()], a waiter on either group never sees the item
+    /// uncounted. A released guard moves nothing and returns a released guard.
+    ///
+    /// Return Err with this guard untouched if `to` rejects the add by a non-panicking
+    /// [ErrorPolicy], eg. closed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate atomic_waitgroup;
+    /// use atomic_waitgroup::WaitGroup;
+    ///
+    /// let (parse, store) = (WaitGroup::new(), WaitGroup::new());
+    /// let guard = parse.add_guard();
+    /// // Parsed
+    /// let guard = guard.handoff(&store).ok().unwrap();
+    /// assert_eq!((parse.left(), store.left()), (0, 1));
+    /// drop(guard);
+    /// assert_eq!(store.left(), 0);
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn handoff(self, to: &GenericWaitGroup<S>) -> Result<Self, Self> {
+        if !self.armed {
+            return Ok(GenericWaitGroupGuard::with_armed(to.0.clone(), false));
+        }
+        if !to.0.raw().add(1) {
+            return Err(self);
+        }
+        drop(self);
+        Ok(GenericWaitGroupGuard::new(to.0.clone()))
+    }
+}
+
+pub type WaitGroupGuard = GenericWaitGroupGuard<SharedArc<WaitGroupInner>>;
+
+impl WaitGroupGuard {
+    /// Leak the guard as a pointer-sized raw pointer, to be released by from_raw().
+    ///
+    /// # Panics
+    ///
+    /// If the guard is released, the pointer has no room for the state.
+    #[inline]
+    pub fn into_raw(self) -> *const c_void {
+        assert!(self.armed, "into_raw() of a released guard");
+        let guard = ManuallyDrop::new(self);
+        // Safety: the guard is never dropped, its Arc is moved out
+        let inner = unsafe { ptr::read(&guard.inner) };
+        SharedArc::into_raw(inner) as *const c_void
+    }
+
+    /// Reassemble a guard from into_raw().
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from [WaitGroupGuard::into_raw()], and not be used again afterwards.
+    #[inline]
+    pub unsafe fn from_raw(ptr: *const c_void) -> Self {
+        GenericWaitGroupGuard::new(SharedArc::from_raw(ptr as *const WaitGroupInner))
+    }
+}
+
+impl<S: Storage> Drop for GenericWaitGroupGuard<S> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.inner.raw().guard_done();
+        }
+    }
+}
+
+struct WaitGroupFuture<'a, R: RawWaitGroup> {
+    wg: &'a R,
+    target: usize,
+    waker_id: u64,
+    caller: &'static Location<'static>,
+}
+
+impl<'a, R: RawWaitGroup> WaitGroupFuture<'a, R> {
+    /// The count satisfying the target
+    #[inline(always)]
+    fn _poll(&mut self) -> Option<u64> {
+        let cur = self.wg.load_left();
+        if cur <= self.target as u64 && !self.wg.is_frozen() {
+            self._clear(true);
+            Some(cur)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn _clear(&mut self, satisfied: bool) {
+        if self.waker_id == 0 {
+            return;
+        }
+        self.wg.cancel_wait(self.waker_id, satisfied);
+        self.waker_id = 0;
+    }
+}
+
+/// When wait() is canceled with timeout(),  make sure it clear the waker.
+impl<'a, R: RawWaitGroup> Drop for WaitGroupFuture<'a, R> {
+    fn drop(&mut self) {
+        self._clear(false);
+    }
+}
+
+impl<'a, R: RawWaitGroup> Future for WaitGroupFuture<'a, R> {
+    type Output = u64;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        if _self.waker_id == 0 {
+            hooks::point(Point::LoadLeft);
+            if let Some(left) = _self._poll() {
+                return Poll::Ready(left);
+            }
+            if ctx.waker().will_wake(Waker::noop()) {
+                // Busy-polled, nothing to wake
+                return Poll::Pending;
+            }
+            hooks::point(Point::SetWaiting);
+            _self.waker_id =
+                _self
+                    .wg
+                    .set_waker(ctx.waker().clone(), _self.target as u64, _self.caller);
+        } else if !_self.wg.update_waker(_self.waker_id, ctx.waker()) {
+            // Dropped by reset(), register again
+            _self.waker_id =
+                _self
+                    .wg
+                    .set_waker(ctx.waker().clone(), _self.target as u64, _self.caller);
+        }
+        hooks::point(Point::Recheck);
+        match _self._poll() {
+            Some(left) => Poll::Ready(left),
+            None => Poll::Pending,
+        }
+    }
+}
+
+struct WaitGroupDeadlineFuture<'a> {
+    f: WaitGroupFuture<'a, WaitGroupInner>,
+    deadline: Instant,
+    timer_id: u64,
+    /// The deadline only to check the progress, the wait is not given up on it
+    recheck: bool,
+}
+
+impl<'a> WaitGroupDeadlineFuture<'a> {
+    #[inline(always)]
+    fn _clear_timer(&mut self) {
+        if self.timer_id == 0 {
+            return;
+        }
+        self.f.wg.deadlines().cancel(self.timer_id);
+        self.timer_id = 0;
+    }
+}
+
+impl<'a> Drop for WaitGroupDeadlineFuture<'a> {
+    fn drop(&mut self) {
+        self._clear_timer();
+    }
+}
+
+impl<'a> Future for WaitGroupDeadlineFuture<'a> {
+    type Output = Result<(), Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        if Pin::new(&mut _self.f).poll(ctx).is_ready() {
+            _self._clear_timer();
+            return Poll::Ready(Ok(()));
+        }
+        if Instant::now() >= _self.deadline {
+            _self._clear_timer();
+            _self.f._clear(_self.recheck);
+            return Poll::Ready(Err(Elapsed));
+        }
+        let deadlines = _self.f.wg.deadlines();
+        if _self.timer_id == 0 {
+            _self.timer_id = deadlines.register(_self.deadline, ctx.waker().clone());
+        } else {
+            deadlines.update(_self.timer_id, ctx.waker());
+        }
+        Poll::Pending
+    }
+}
+
+struct WaitGroupProgressFuture<'a, R: RawWaitGroup, F: FnMut(usize)> {
+    wg: &'a R,
+    target: usize,
+    last: u64,
+    progress: F,
+    waker_id: u64,
+    caller: &'static Location<'static>,
+}
+
+// The closure is never pinned
+impl<'a, R: RawWaitGroup, F: FnMut(usize)> Unpin for WaitGroupProgressFuture<'a, R, F> {}
+
+impl<'a, R: RawWaitGroup, F: FnMut(usize)> WaitGroupProgressFuture<'a, R, F> {
+    #[inline(always)]
+    fn _poll(&mut self) -> bool {
+        let cur = self.wg.load_left();
+        if cur < self.last {
+            self.last = cur;
+            (self.progress)(cur as usize);
+        }
+        if cur <= self.target as u64 && !self.wg.is_frozen() {
+            self._clear(true);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[inline(always)]
+    fn _clear(&mut self, satisfied: bool) {
+        if self.waker_id == 0 {
+            return;
+        }
+        self.wg.cancel_wait(self.waker_id, satisfied);
+        self.waker_id = 0;
+    }
+}
+
+impl<'a, R: RawWaitGroup, F: FnMut(usize)> Drop for WaitGroupProgressFuture<'a, R, F> {
+    fn drop(&mut self) {
+        self._clear(false);
+    }
+}
+
+impl<'a, R: RawWaitGroup, F: FnMut(usize)> Future for WaitGroupProgressFuture<'a, R, F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        if _self.waker_id == 0 {
+            if _self._poll() {
+                return Poll::Ready(());
+            }
+            // Wake on every done() so that each step can be reported
+            _self.waker_id = _self
+                .wg
+                .set_waker(ctx.waker().clone(), WAKE_ALWAYS, _self.caller);
+        } else if !_self.wg.update_waker(_self.waker_id, ctx.waker()) {
+            // Dropped by reset()
+            _self.waker_id = _self
+                .wg
+                .set_waker(ctx.waker().clone(), WAKE_ALWAYS, _self.caller);
+        }
+        if _self._poll() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::time::Duration;
+    use tokio::time::{sleep, timeout};
+
+    use super::*;
 
     fn make_runtime(threads: usize) -> tokio::runtime::Runtime {
-        return tokio::runtime::Builder::new_multi_thread()
+        tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .worker_threads(threads)
             .build()
-            .unwrap();
+            .unwrap()
     }
 
     #[test]
@@ -336,24 +2537,114 @@ mod tests {
             wg.add(2);
             let _wg = wg.clone();
             let th = tokio::spawn(async move {
-                assert!(_wg.wait_to(1).await);
+                assert!(_wg.wait_to(1).await.blocked);
             });
             sleep(Duration::from_secs(1)).await;
             assert_eq!(wg.0.waker_id.load(Ordering::Acquire), 1);
-            {
-                let guard = wg.0.waker.lock();
+            wg.0.waker.with(|guard| {
                 assert!(guard.is_some());
                 assert_eq!(wg.0.waiting.load(Ordering::Acquire), 1);
-            }
+            });
             wg.done();
             let _ = th.await;
             assert_eq!(wg.0.waker_id.load(Ordering::Acquire), 1);
-            assert_eq!(wg.0.waiting.load(Ordering::Acquire), -1);
+            assert_eq!(wg.0.waiting.load(Ordering::Acquire), NO_WAITER);
             assert_eq!(wg.left(), 1);
             wg.done();
             assert_eq!(wg.left(), 0);
-            assert_eq!(wg.wait_to(0).await, false);
+            assert!(!wg.wait_to(0).await.blocked);
+        });
+    }
+
+    #[test]
+    fn test_deadline() {
+        let wg = WaitGroup::new();
+        make_runtime(1).block_on(async move {
+            wg.add(1);
+            let deadline = Instant::now() + Duration::from_millis(200);
+            assert_eq!(wg.wait_to_deadline(0, deadline).await, Err(Elapsed));
+            assert!(Instant::now() >= deadline);
+            assert_eq!(wg.0.waiting.load(Ordering::Acquire), NO_WAITER);
+            // Canceled, the entries are pruned once they outnumber the live timers
+            let far = Instant::now() + Duration::from_secs(3600);
+            for _ in 0..200 {
+                let f = wg.wait_to_deadline(0, far);
+                assert!(timeout(Duration::from_millis(0), f).await.is_err());
+            }
+            {
+                use std::cmp::Reverse;
+                let state = wg.0.deadlines().shared.state.lock();
+                let dead = state
+                    .heap
+                    .iter()
+                    .filter(|Reverse((deadline, _))| *deadline == far)
+                    .count();
+                assert!(dead < 200);
+            }
+            let _wg = wg.clone();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(100)).await;
+                _wg.done();
+            });
+            let deadline = Instant::now() + Duration::from_secs(10);
+            assert_eq!(wg.wait_to_deadline(0, deadline).await, Ok(true));
+            assert_eq!(wg.wait_to_deadline(0, deadline).await, Ok(false));
+        });
+    }
+
+    #[test]
+    fn test_done_from_isr() {
+        make_runtime(1).block_on(async move {
+            let wg = WaitGroup::new();
+            wg.add(2);
+            let _wg = wg.clone();
+            let th = tokio::spawn(async move {
+                _wg.wait().await;
+            });
+            sleep(Duration::from_millis(100)).await;
+            // Contended waker slot, the wake is left to the holder
+            wg.0.with_waker(|_| {
+                wg.done_from_isr();
+                wg.done_from_isr();
+                assert!(wg.0.wake_pending.load(Ordering::SeqCst));
+            });
+            let _ = th.await;
+            assert!(!wg.0.wake_pending.load(Ordering::SeqCst));
+            assert_eq!(wg.left(), 0);
+        });
+    }
+
+    #[cfg(all(feature = "stats", not(feature = "critical-section")))]
+    #[test]
+    fn test_stats_contention() {
+        use std::future::Future;
+        use std::task::Context;
+
+        let wg = WaitGroup::new();
+        wg.add(1);
+        let mut f = Box::pin(wg.wait());
+        let mut ctx = Context::from_waker(Waker::noop());
+        std::thread::scope(|s| {
+            wg.0.with_waker(|_| {
+                s.spawn(|| {
+                    // Registers after the slot is released, a noop waker is not registered
+                    let waker = futures::task::noop_waker();
+                    assert!(!waker.will_wake(Waker::noop()));
+                    let mut ctx = Context::from_waker(&waker);
+                    assert!(f.as_mut().poll(&mut ctx).is_pending());
+                });
+                std::thread::sleep(Duration::from_millis(50));
+            });
+        });
+        assert_eq!(wg.stats().register_contended, 1);
+        std::thread::scope(|s| {
+            wg.0.with_waker(|_| {
+                s.spawn(|| wg.done());
+                std::thread::sleep(Duration::from_millis(50));
+            });
         });
+        assert_eq!(wg.stats().done_contended, 1);
+        assert!(f.as_mut().poll(&mut ctx).is_ready());
     }
 
     #[test]
@@ -364,7 +2655,7 @@ mod tests {
             println!("test timeout");
             assert!(timeout(Duration::from_secs(1), wg.wait()).await.is_err());
             println!("timeout happened");
-            assert_eq!(wg.0.waiting.load(Ordering::Acquire), -1);
+            assert_eq!(wg.0.waiting.load(Ordering::Acquire), NO_WAITER);
             wg.done();
             wg.add(2);
             wg.done_many(2);
@@ -0,0 +1,270 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    listener::{Listener, ListenerHandle},
+    WaitGroup, WaitGroupGuard,
+};
+
+/// Multiplier of the limit when completions fall behind arrivals
+const DECREASE: f64 = 0.75;
+
+const DEFAULT_WINDOW: Duration = Duration::from_millis(100);
+
+/// Admission control on top of a WaitGroup, with the limit of its count tuned by AIMD.
+///
+/// At the end of every window, if arrivals outran completions by more than the limit, the
+/// limit is multiplied by 0.75. Otherwise if the limit was reached, it increases by one.
+/// The limit stays within `min..=max`.
+///
+/// The limit applies to the count of the WaitGroup, including add() not through the limiter,
+/// which is not blocked.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{AdaptiveLimiter, WaitGroup};
+/// use tokio::runtime::Runtime;
+///
+/// let wg = WaitGroup::new();
+/// let limiter = AdaptiveLimiter::new(wg.clone(), 2, 16);
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     for _ in 0..10 {
+///         let _guard = limiter.acquire().await;
+///         tokio::spawn(async move {
+///             // Do something
+///             drop(_guard);
+///         });
+///     }
+///     wg.wait().await;
+/// });
+/// ```
+pub struct AdaptiveLimiter {
+    wg: WaitGroup,
+    shared: Arc<LimiterShared>,
+    _handle: ListenerHandle,
+}
+
+struct LimiterShared {
+    limit: AtomicUsize,
+    min: usize,
+    max: usize,
+    window: Mutex<Window>,
+    waiters: Mutex<VecDeque<(u64, Waker)>>,
+    waiter_id: AtomicU64,
+}
+
+struct Window {
+    length: Duration,
+    start: Instant,
+    arrived: u64,
+    completed: u64,
+    saturated: bool,
+}
+
+impl LimiterShared {
+    /// Adjust the limit at the end of the window
+    fn record(&self, arrived: u64, completed: u64, saturated: bool) {
+        let mut window = self.window.lock();
+        window.arrived += arrived;
+        window.completed += completed;
+        window.saturated |= saturated;
+        let now = Instant::now();
+        if now.duration_since(window.start) < window.length {
+            return;
+        }
+        let limit = self.limit.load(Ordering::Acquire);
+        // Arrivals admitted but not completed by the end of the window are not a backlog
+        if window.arrived > window.completed + limit as u64 {
+            let decreased = (limit as f64 * DECREASE) as usize;
+            self.limit.store(decreased.max(self.min), Ordering::Release);
+        } else if window.saturated && window.arrived > 0 {
+            self.limit
+                .store((limit + 1).min(self.max), Ordering::Release);
+        }
+        window.start = now;
+        window.arrived = 0;
+        window.completed = 0;
+        window.saturated = false;
+    }
+
+    fn wake(&self, left: u64) {
+        let free = (self.limit.load(Ordering::Acquire) as u64).saturating_sub(left);
+        if free == 0 {
+            return;
+        }
+        let mut waiters = self.waiters.lock();
+        for _ in 0..free {
+            match waiters.pop_front() {
+                Some((_, waker)) => waker.wake(),
+                None => return,
+            }
+        }
+    }
+}
+
+impl Listener for LimiterShared {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        self.record(0, old - left, false);
+        self.wake(left);
+    }
+}
+
+impl AdaptiveLimiter {
+    /// Start with the limit at `min`.
+    ///
+    /// # Panics
+    ///
+    /// If `min` is zero or larger than `max`.
+    pub fn new(wg: WaitGroup, min: usize, max: usize) -> Self {
+        assert!(
+            min > 0 && min <= max,
+            "AdaptiveLimiter requires 0 < min <= max"
+        );
+        let shared = Arc::new(LimiterShared {
+            limit: AtomicUsize::new(min),
+            min,
+            max,
+            window: Mutex::new(Window {
+                length: DEFAULT_WINDOW,
+                start: Instant::now(),
+                arrived: 0,
+                completed: 0,
+                saturated: false,
+            }),
+            waiters: Mutex::new(VecDeque::new()),
+            waiter_id: AtomicU64::new(0),
+        });
+        let _handle = ListenerHandle::register(wg.0.clone(), shared.clone());
+        Self {
+            wg,
+            shared,
+            _handle,
+        }
+    }
+
+    /// Length of the window to compare arrivals with completions, default to 100ms.
+    pub fn with_window(self, window: Duration) -> Self {
+        self.shared.window.lock().length = window;
+        self
+    }
+
+    /// The current limit
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.shared.limit.load(Ordering::Acquire)
+    }
+
+    /// The WaitGroup limited
+    #[inline]
+    pub fn wait_group(&self) -> &WaitGroup {
+        &self.wg
+    }
+
+    /// Add one to the WaitGroup when the count is below the limit, otherwise wait.
+    ///
+    /// Canceling future is supported.
+    #[inline]
+    pub fn acquire(&self) -> AcquireFuture<'_> {
+        AcquireFuture {
+            limiter: self,
+            waiter_id: 0,
+            arrived: false,
+        }
+    }
+
+    /// Add one to the WaitGroup if the count is below the limit.
+    pub fn try_acquire(&self) -> Option<WaitGroupGuard> {
+        self.admit(true)
+    }
+
+    #[inline]
+    fn admit(&self, arrival: bool) -> Option<WaitGroupGuard> {
+        let arrived = arrival as u64;
+        // Serialized with the window, so that concurrent acquire() do not exceed the limit
+        let window = self.shared.window.lock();
+        if self.wg.left() < self.limit() {
+            let guard = self.wg.add_guard();
+            drop(window);
+            self.shared.record(arrived, 0, false);
+            Some(guard)
+        } else {
+            drop(window);
+            self.shared.record(arrived, 0, true);
+            None
+        }
+    }
+}
+
+/// Future returned by [AdaptiveLimiter::acquire()].
+pub struct AcquireFuture<'a> {
+    limiter: &'a AdaptiveLimiter,
+    waiter_id: u64,
+    arrived: bool,
+}
+
+impl AcquireFuture<'_> {
+    /// Return false if already woken
+    fn cancel(&mut self) -> bool {
+        if self.waiter_id == 0 {
+            return true;
+        }
+        let id = self.waiter_id;
+        self.waiter_id = 0;
+        let mut waiters = self.limiter.shared.waiters.lock();
+        let len = waiters.len();
+        waiters.retain(|(_id, _)| *_id != id);
+        waiters.len() != len
+    }
+}
+
+impl Drop for AcquireFuture<'_> {
+    fn drop(&mut self) {
+        if !self.cancel() {
+            // Pass the wake on to the next waiter
+            let left = self.limiter.wg.0.left.load(Ordering::Acquire);
+            self.limiter.shared.wake(left);
+        }
+    }
+}
+
+impl Future for AcquireFuture<'_> {
+    type Output = WaitGroupGuard;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        let arrival = !_self.arrived;
+        _self.arrived = true;
+        _self.cancel();
+        if let Some(guard) = _self.limiter.admit(arrival) {
+            return Poll::Ready(guard);
+        }
+        let shared = &_self.limiter.shared;
+        let waiter_id = shared.waiter_id.fetch_add(1, Ordering::Relaxed) + 1;
+        shared
+            .waiters
+            .lock()
+            .push_back((waiter_id, ctx.waker().clone()));
+        _self.waiter_id = waiter_id;
+        // done() might happen before the waker is queued
+        if let Some(guard) = _self.limiter.admit(false) {
+            _self.cancel();
+            return Poll::Ready(guard);
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use crate::{inner::SharedArc, WaitGroupInner};
+
+/// Internal hook called on every done(), for components other than the single waiter.
+pub(crate) trait Listener: Send + Sync {
+    /// Count decreased from `old` to `left`
+    fn on_done(&self, old: u64, left: u64);
+}
+
+/// Remove the listener on drop
+pub(crate) struct ListenerHandle {
+    inner: SharedArc<WaitGroupInner>,
+    listener: Arc<dyn Listener>,
+}
+
+impl ListenerHandle {
+    pub(crate) fn register(inner: SharedArc<WaitGroupInner>, listener: Arc<dyn Listener>) -> Self {
+        {
+            let mut listeners = inner.listeners.write();
+            listeners.push(listener.clone());
+            inner.has_listeners.store(true, Ordering::SeqCst);
+        }
+        Self { inner, listener }
+    }
+}
+
+impl Drop for ListenerHandle {
+    fn drop(&mut self) {
+        let mut listeners = self.inner.listeners.write();
+        listeners.retain(|l| !Arc::ptr_eq(l, &self.listener));
+        if listeners.is_empty() {
+            self.inner.has_listeners.store(false, Ordering::SeqCst);
+        }
+    }
+}
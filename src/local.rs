@@ -0,0 +1,171 @@
+use log::error;
+use std::{
+    cell::{Cell, RefCell},
+    panic::Location,
+    rc::Rc,
+    task::Waker,
+};
+
+use crate::{
+    chaos,
+    diagnostics::{self, Op},
+    storage::{sealed::Sealed, RawWaitGroup, Storage, NO_WAITER},
+    GenericWaitGroup, GenericWaitGroupGuard,
+};
+
+/// A WaitGroup for single-threaded use (thread-per-core runtimes, tokio LocalSet),
+/// without atomic operations or locking.
+///
+/// Same API as [crate::WaitGroup] on the shared part, only one waiting future at the same time.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::LocalWaitGroup;
+/// use tokio::task::LocalSet;
+///
+/// let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let local = LocalSet::new();
+/// let wg = LocalWaitGroup::new();
+/// local.block_on(&rt, async move {
+///     for _ in 0..2 {
+///         let _guard = wg.add_guard();
+///         tokio::task::spawn_local(async move {
+///             // Do something
+///             drop(_guard);
+///         });
+///     }
+///     wg.wait().await;
+/// });
+/// ```
+pub type LocalWaitGroup = GenericWaitGroup<Rc<LocalWaitGroupInner>>;
+
+pub type LocalWaitGroupGuard = GenericWaitGroupGuard<Rc<LocalWaitGroupInner>>;
+
+pub struct LocalWaitGroupInner {
+    pub(crate) left: Cell<u64>,
+    pub(crate) waiting: Cell<u64>,
+    pub(crate) waker: RefCell<Option<Waker>>,
+    pub(crate) waker_id: Cell<u64>,
+}
+
+impl RawWaitGroup for LocalWaitGroupInner {
+    const NAME: &'static str = "LocalWaitGroup";
+
+    #[inline(always)]
+    fn load_left(&self) -> u64 {
+        self.left.get()
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    fn add(&self, count: usize) -> bool {
+        let left = self
+            .left
+            .get()
+            .checked_add(count as u64)
+            .filter(|left| *left <= usize::MAX as u64)
+            .unwrap_or_else(|| panic!("LocalWaitGroup.left overflow at {}", Location::caller()));
+        self.left.set(left);
+        diagnostics::emit(self, Op::Add(count), left, Location::caller());
+        true
+    }
+
+    #[inline]
+    #[track_caller]
+    fn done(&self, count: usize) -> bool {
+        let old = self.left.get();
+        let Some(left) = old.checked_sub(count as u64) else {
+            error!(
+                "LocalWaitGroup.left {} - {} < 0 at {}",
+                old,
+                count,
+                Location::caller()
+            );
+            panic!(
+                "LocalWaitGroup.left {} - {} < 0 at {}",
+                old,
+                count,
+                Location::caller()
+            );
+        };
+        self.left.set(left);
+        diagnostics::emit(self, Op::Done(count), left, Location::caller());
+        let waiting = self.waiting.get();
+        if waiting != NO_WAITER && left <= waiting {
+            if let Some(waker) = self.waker.borrow().as_ref() {
+                chaos::wake(waker);
+            }
+        }
+        true
+    }
+
+    #[inline]
+    fn set_waker(&self, waker: Waker, wake_at: u64, caller: &'static Location<'static>) -> u64 {
+        let waker_id = self.waker_id.get() + 1;
+        self.waker_id.set(waker_id);
+        if self.waiting.replace(wake_at) != NO_WAITER {
+            panic!(
+                "Concurrent wait() by multiple coroutines is not supported at {}",
+                caller
+            )
+        }
+        self.waker.borrow_mut().replace(waker);
+        diagnostics::emit(
+            self,
+            Op::SetWaker { waker_id, wake_at },
+            self.left.get(),
+            caller,
+        );
+        waker_id
+    }
+
+    #[inline]
+    fn update_waker(&self, waker_id: u64, waker: &Waker) -> bool {
+        if self.waker_id.get() != waker_id {
+            return false;
+        }
+        let mut guard = self.waker.borrow_mut();
+        match guard.as_ref() {
+            Some(old) if old.will_wake(waker) => {}
+            _ => *guard = Some(waker.clone()),
+        }
+        true
+    }
+
+    #[inline]
+    fn cancel_wait(&self, waker_id: u64, _satisfied: bool) {
+        if self.waker_id.get() == waker_id {
+            self.waiting.set(NO_WAITER);
+            let _ = self.waker.borrow_mut().take();
+        }
+        diagnostics::emit(
+            self,
+            Op::Cancel { waker_id },
+            self.left.get(),
+            Location::caller(),
+        );
+    }
+}
+
+impl Sealed for Rc<LocalWaitGroupInner> {}
+
+impl Storage for Rc<LocalWaitGroupInner> {
+    type Raw = LocalWaitGroupInner;
+
+    #[inline(always)]
+    fn create() -> Self {
+        Rc::new(LocalWaitGroupInner {
+            left: Cell::new(0),
+            waiting: Cell::new(NO_WAITER),
+            waker: RefCell::new(None),
+            waker_id: Cell::new(0),
+        })
+    }
+
+    #[inline(always)]
+    fn raw(&self) -> &LocalWaitGroupInner {
+        self.as_ref()
+    }
+}
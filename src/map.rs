@@ -0,0 +1,187 @@
+use std::{collections::HashMap, future::Future, hash::Hash, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::{WaitGroup, WaitGroupGuard, WaitOutcome};
+
+/// One WaitGroup per key, eg. to track in-flight work per tenant.
+///
+/// The group of a key is created by the first add_guard(), and removed when its count drops
+/// to zero, so idle keys do not accumulate.
+///
+/// # NOTE
+///
+/// * Like WaitGroup, only one wait_key() per key at the same time, and one wait_all_keys()
+///   at the same time.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::WaitGroupMap;
+/// use tokio::runtime::Runtime;
+///
+/// let map = WaitGroupMap::new();
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     for tenant in ["a", "b", "a"] {
+///         let _guard = map.add_guard(tenant);
+///         tokio::spawn(async move {
+///             // Do something
+///             drop(_guard);
+///         });
+///     }
+///     map.wait_key(&"a").await;
+///     map.wait_all_keys().await;
+///     assert!(map.keys().is_empty());
+/// });
+/// ```
+pub struct WaitGroupMap<K> {
+    shared: Arc<MapShared<K>>,
+}
+
+struct MapShared<K> {
+    groups: Mutex<HashMap<K, WaitGroup>>,
+    all: WaitGroup,
+}
+
+/// Count of a key in a [WaitGroupMap], returned by [WaitGroupMap::stats()].
+///
+/// The totals start from the creation of the group of the key, which is removed whenever the
+/// count of the key drops to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStats {
+    pub left: usize,
+    /// Total count increased
+    pub added: u64,
+    /// Total count decreased
+    pub completed: u64,
+}
+
+/// Decrease the count of its key on drop, created by [WaitGroupMap::add_guard()].
+pub struct KeyGuard<K: Eq + Hash> {
+    shared: Arc<MapShared<K>>,
+    key: Option<K>,
+    // Dropped after the key, so wait_all_keys() returns after the groups are removed
+    _all: WaitGroupGuard,
+}
+
+impl<K: Eq + Hash + Clone> WaitGroupMap<K> {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(MapShared {
+                groups: Mutex::new(HashMap::new()),
+                all: WaitGroup::new(),
+            }),
+        }
+    }
+
+    /// Add one to the group of `key`, created if absent.
+    #[inline]
+    pub fn add_guard(&self, key: K) -> KeyGuard<K> {
+        let _all = self.shared.all.add_guard();
+        self.shared
+            .groups
+            .lock()
+            .entry(key.clone())
+            .or_default()
+            .add(1);
+        KeyGuard {
+            shared: self.shared.clone(),
+            key: Some(key),
+            _all,
+        }
+    }
+
+    /// The count of `key`, zero if it has no group.
+    #[inline]
+    pub fn left(&self, key: &K) -> usize {
+        self.shared.groups.lock().get(key).map_or(0, |wg| wg.left())
+    }
+
+    /// Total count of all keys
+    #[inline]
+    pub fn left_all(&self) -> usize {
+        self.shared.all.left()
+    }
+
+    /// Keys with count above zero
+    pub fn keys(&self) -> Vec<K> {
+        self.shared.groups.lock().keys().cloned().collect()
+    }
+
+    /// Count and totals of `key`, None if it has no group.
+    pub fn stats(&self, key: &K) -> Option<KeyStats> {
+        self.shared.groups.lock().get(key).map(|wg| {
+            let snapshot = wg.snapshot();
+            KeyStats {
+                left: wg.left(),
+                added: snapshot.added,
+                completed: snapshot.completed,
+            }
+        })
+    }
+
+    /// Wait until the count of `key` drops to zero.
+    ///
+    /// Canceling future is supported.
+    #[inline]
+    pub fn wait_key(&self, key: &K) -> impl Future<Output = ()> + 'static {
+        let f = self.wait_key_to(key, 0);
+        async move {
+            f.await;
+        }
+    }
+
+    /// Wait until the count of `key` is less than or equal to `target`, while other keys
+    /// continue, eg. to flush the writes of one category during a drain.
+    ///
+    /// Return false means there's no waiting happened.
+    ///
+    /// Canceling future is supported.
+    pub fn wait_key_to(&self, key: &K, target: usize) -> impl Future<Output = bool> + 'static {
+        let wg = self.shared.groups.lock().get(key).cloned();
+        async move {
+            match wg {
+                Some(wg) => wg.wait_to(target).await.blocked,
+                None => false,
+            }
+        }
+    }
+
+    /// Wait until the count of every key drops to zero.
+    ///
+    /// Canceling future is supported.
+    #[inline]
+    pub fn wait_all_keys(&self) -> impl Future<Output = WaitOutcome> + '_ {
+        self.shared.all.wait()
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for WaitGroupMap<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash> KeyGuard<K> {
+    /// The key of the group
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.key.as_ref().unwrap()
+    }
+}
+
+impl<K: Eq + Hash> Drop for KeyGuard<K> {
+    fn drop(&mut self) {
+        let key = self.key.take().unwrap();
+        // Under the lock, so that add_guard() never adds to a group being removed
+        let mut groups = self.shared.groups.lock();
+        if let Some(wg) = groups.get(&key) {
+            wg.done();
+            if wg.left() == 0 {
+                groups.remove(&key);
+            }
+        }
+    }
+}
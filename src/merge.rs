@@ -0,0 +1,66 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::{inner::SharedArc, listener::Listener, storage::RawWaitGroup, WaitGroupInner};
+
+/// Forward done() of a merged WaitGroup to the target, for the count absorbed at merge time.
+struct MergeListener {
+    target: SharedArc<WaitGroupInner>,
+    /// Count of the merged WaitGroup at merge time
+    merged: u64,
+    /// Count not yet forwarded
+    remaining: AtomicU64,
+}
+
+impl Listener for MergeListener {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        // Concurrent done() decrease disjoint ranges, only the part below the merged count
+        // belongs to the target. Ranges above are from add() after the merge.
+        let part = old.min(self.merged).saturating_sub(left);
+        if part == 0 {
+            return;
+        }
+        let mut remaining = self.remaining.load(Ordering::Acquire);
+        loop {
+            let take = part.min(remaining);
+            if take == 0 {
+                return;
+            }
+            match self.remaining.compare_exchange_weak(
+                remaining,
+                remaining - take,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    self.target.done(take as usize);
+                    return;
+                }
+                Err(r) => remaining = r,
+            }
+        }
+    }
+}
+
+pub(crate) fn merge(target: &SharedArc<WaitGroupInner>, other: &SharedArc<WaitGroupInner>) {
+    // Listeners are called under the read lock, no done() is forwarded before add()
+    let mut listeners = other.listeners.write();
+    other.has_listeners.store(true, Ordering::SeqCst);
+    // done() which missed the listener decreased above this count
+    let merged = other.left.load(Ordering::SeqCst);
+    if merged == 0 {
+        if listeners.is_empty() {
+            other.has_listeners.store(false, Ordering::SeqCst);
+        }
+        return;
+    }
+    target.add(merged as usize);
+    listeners.push(Arc::new(MergeListener {
+        target: target.clone(),
+        merged,
+        remaining: AtomicU64::new(merged),
+    }));
+}
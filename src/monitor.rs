@@ -0,0 +1,115 @@
+//! Live view of the WaitGroups registered by name, enabled with the `monitor` feature.
+//!
+//! A group is listed from [crate::WaitGroup::register_monitor()] until its registration is
+//! dropped. [watch()] redraws the table on a terminal, eg. from a debug thread of a service
+//! stuck in shutdown.
+
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use parking_lot::{const_mutex, Mutex};
+
+use crate::{DrainState, WaitGroup};
+
+static GROUPS: Mutex<Vec<(u64, String, WaitGroup)>> = const_mutex(Vec::new());
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registration created by [crate::WaitGroup::register_monitor()], removes the group from the
+/// monitor on drop.
+///
+/// The monitor holds a handle of the WaitGroup while registered.
+#[must_use = "dropping the registration removes the group from the monitor"]
+pub struct MonitorRegistration {
+    id: u64,
+}
+
+impl Drop for MonitorRegistration {
+    fn drop(&mut self) {
+        GROUPS.lock().retain(|(id, _, _)| *id != self.id);
+    }
+}
+
+/// A registered group at the moment of [rows()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MonitorRow {
+    pub name: String,
+    pub left: usize,
+    /// Async and blocking waits in progress
+    pub waiters: usize,
+    /// How long the oldest guard registered with the `diagnostics` feature has been
+    /// outstanding, always None without the feature.
+    pub oldest_age: Option<Duration>,
+    pub state: DrainState,
+}
+
+pub(crate) fn register(wg: &WaitGroup, name: String) -> MonitorRegistration {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    GROUPS.lock().push((id, name, wg.clone()));
+    MonitorRegistration { id }
+}
+
+/// The registered groups, in registration order
+pub fn rows() -> Vec<MonitorRow> {
+    GROUPS
+        .lock()
+        .iter()
+        .map(|(_, name, wg)| {
+            let blocked = wg.0.blocking.get().map_or(0, |waiters| waiters.blocked());
+            #[cfg(feature = "diagnostics")]
+            let oldest_age = wg.oldest_outstanding_age();
+            #[cfg(not(feature = "diagnostics"))]
+            let oldest_age = None;
+            MonitorRow {
+                name: name.clone(),
+                left: wg.left(),
+                waiters: wg.is_waiting().is_some() as usize + blocked,
+                oldest_age,
+                state: wg.state(),
+            }
+        })
+        .collect()
+}
+
+/// Write the registered groups as a table.
+pub fn render<W: Write>(out: &mut W) -> io::Result<()> {
+    let rows = rows();
+    let width = rows
+        .iter()
+        .map(|row| row.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    writeln!(
+        out,
+        "{:<width$}  {:>10}  {:>7}  {:>10}  STATE",
+        "NAME", "LEFT", "WAITERS", "OLDEST_MS"
+    )?;
+    for row in rows {
+        let oldest = match row.oldest_age {
+            Some(age) => age.as_millis().to_string(),
+            None => "-".to_string(),
+        };
+        writeln!(
+            out,
+            "{:<width$}  {:>10}  {:>7}  {:>10}  {:?}",
+            row.name, row.left, row.waiters, oldest, row.state
+        )?;
+    }
+    Ok(())
+}
+
+/// Clear the terminal and render() every `interval`, until writing fails.
+pub fn watch<W: Write>(out: &mut W, interval: Duration) -> io::Result<()> {
+    loop {
+        // Clear the screen and move to the top left
+        write!(out, "\x1b[2J\x1b[H")?;
+        render(out)?;
+        out.flush()?;
+        std::thread::sleep(interval);
+    }
+}
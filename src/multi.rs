@@ -0,0 +1,225 @@
+use log::error;
+use std::{
+    panic::Location,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    task::Waker,
+};
+
+use crate::{
+    chaos,
+    diagnostics::{self, Op},
+    inner::SharedArc,
+    storage::{sealed::Sealed, RawWaitGroup, Storage, NO_WAITER},
+    waker_slot::WakerSlot,
+    GenericWaitGroup, GenericWaitGroupGuard,
+};
+
+/// A WaitGroup allowing up to `N` waiting futures at the same time.
+///
+/// The waiters are kept in a fixed-size array inside the WaitGroup, nothing is allocated per
+/// wait. done() checks every slot, so keep `N` small.
+///
+/// # NOTE
+///
+/// * Requires std like the rest of the crate, there is no no_std build.
+///
+/// # Panics
+///
+/// When more than `N` futures are waiting at the same time.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::MultiWaitGroup;
+/// use tokio::runtime::Runtime;
+///
+/// let wg = MultiWaitGroup::<4>::new();
+/// let rt = Runtime::new().unwrap();
+///
+/// rt.block_on(async move {
+///     let _guard = wg.add_guard();
+///     let _wg = wg.clone();
+///     let th = tokio::spawn(async move {
+///         _wg.wait().await;
+///     });
+///     tokio::spawn(async move {
+///         // Do something
+///         drop(_guard);
+///     });
+///     wg.wait().await;
+///     let _ = th.await;
+/// });
+/// ```
+pub type MultiWaitGroup<const N: usize> = GenericWaitGroup<SharedArc<MultiWaitGroupInner<N>>>;
+
+pub type MultiWaitGroupGuard<const N: usize> =
+    GenericWaitGroupGuard<SharedArc<MultiWaitGroupInner<N>>>;
+
+struct WaiterSlot {
+    used: AtomicBool,
+    waiting: AtomicU64,
+    waker: WakerSlot,
+    waker_id: AtomicU64,
+}
+
+pub struct MultiWaitGroupInner<const N: usize> {
+    left: AtomicU64,
+    waker_id: AtomicU64,
+    slots: [WaiterSlot; N],
+}
+
+impl<const N: usize> RawWaitGroup for MultiWaitGroupInner<N> {
+    const NAME: &'static str = "MultiWaitGroup";
+
+    #[inline(always)]
+    fn load_left(&self) -> u64 {
+        self.left.load(Ordering::Acquire)
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    fn add(&self, count: usize) -> bool {
+        let left = self
+            .left
+            .fetch_update(Ordering::SeqCst, Ordering::Acquire, |old| {
+                old.checked_add(count as u64)
+                    .filter(|left| *left <= usize::MAX as u64)
+            })
+            .map(|old| old + count as u64)
+            .unwrap_or_else(|_| panic!("MultiWaitGroup.left overflow at {}", Location::caller()));
+        diagnostics::emit(self, Op::Add(count), left, Location::caller());
+        true
+    }
+
+    #[inline]
+    #[track_caller]
+    fn done(&self, count: usize) -> bool {
+        chaos::jitter();
+        let left = self
+            .left
+            .fetch_update(Ordering::SeqCst, Ordering::Acquire, |old| {
+                old.checked_sub(count as u64)
+            })
+            .map(|old| old - count as u64)
+            .unwrap_or_else(|old| {
+                error!(
+                    "MultiWaitGroup.left {} - {} < 0 at {}",
+                    old,
+                    count,
+                    Location::caller()
+                );
+                panic!(
+                    "MultiWaitGroup.left {} - {} < 0 at {}",
+                    old,
+                    count,
+                    Location::caller()
+                )
+            });
+        diagnostics::emit(self, Op::Done(count), left, Location::caller());
+        for slot in self.slots.iter() {
+            let waiting = slot.waiting.load(Ordering::Acquire);
+            if waiting != NO_WAITER && left <= waiting {
+                slot.waker.with(|waker| {
+                    if let Some(waker) = waker.as_ref() {
+                        chaos::wake(waker);
+                    }
+                });
+            }
+        }
+        true
+    }
+
+    #[inline]
+    fn set_waker(&self, waker: Waker, wake_at: u64, caller: &'static Location<'static>) -> u64 {
+        let waker_id = self.waker_id.fetch_add(1, Ordering::SeqCst) + 1;
+        for slot in self.slots.iter() {
+            if slot
+                .used
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            slot.waker_id.store(waker_id, Ordering::Release);
+            slot.waker.with(|guard| {
+                guard.replace(waker);
+                slot.waiting.store(wake_at, Ordering::SeqCst);
+            });
+            diagnostics::emit(
+                self,
+                Op::SetWaker { waker_id, wake_at },
+                self.left.load(Ordering::Acquire),
+                caller,
+            );
+            return waker_id;
+        }
+        panic!(
+            "MultiWaitGroup supports at most {} concurrent waiters at {}",
+            N, caller
+        )
+    }
+
+    #[inline]
+    fn update_waker(&self, waker_id: u64, waker: &Waker) -> bool {
+        // Only the owner of `waker_id` releases its slot, which is this caller
+        for slot in self.slots.iter() {
+            if slot.waker_id.load(Ordering::Acquire) != waker_id {
+                continue;
+            }
+            slot.waker.with(|guard| match guard {
+                Some(old) if old.will_wake(waker) => {}
+                _ => *guard = Some(waker.clone()),
+            });
+            return true;
+        }
+        false
+    }
+
+    #[inline]
+    fn cancel_wait(&self, waker_id: u64, _satisfied: bool) {
+        for slot in self.slots.iter() {
+            if slot.waker_id.load(Ordering::Acquire) != waker_id {
+                continue;
+            }
+            slot.waker.with(|guard| {
+                slot.waiting.store(NO_WAITER, Ordering::Release);
+                let _ = guard.take();
+            });
+            slot.waker_id.store(0, Ordering::Release);
+            slot.used.store(false, Ordering::Release);
+            diagnostics::emit(
+                self,
+                Op::Cancel { waker_id },
+                self.left.load(Ordering::Acquire),
+                Location::caller(),
+            );
+            return;
+        }
+    }
+}
+
+impl<const N: usize> Sealed for SharedArc<MultiWaitGroupInner<N>> {}
+
+impl<const N: usize> Storage for SharedArc<MultiWaitGroupInner<N>> {
+    type Raw = MultiWaitGroupInner<N>;
+
+    #[inline]
+    fn create() -> Self {
+        SharedArc::new(MultiWaitGroupInner {
+            left: AtomicU64::new(0),
+            waker_id: AtomicU64::new(0),
+            slots: std::array::from_fn(|_| WaiterSlot {
+                used: AtomicBool::new(false),
+                waiting: AtomicU64::new(NO_WAITER),
+                waker: WakerSlot::new(),
+                waker_id: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    #[inline(always)]
+    fn raw(&self) -> &MultiWaitGroupInner<N> {
+        self.as_ref()
+    }
+}
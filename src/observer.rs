@@ -0,0 +1,59 @@
+use std::{fmt, sync::Arc};
+
+use parking_lot::{const_rwlock, RwLock};
+
+/// Hooks on the transitions of a WaitGroup, for instrumentation backends such as tracing or
+/// metrics, installed with [crate::WaitGroupBuilder::observer()], or for all WaitGroups with
+/// [set_default_observer()].
+///
+/// All methods default to no-op. They are called on the thread of the transition, and should
+/// not block or call back into the same WaitGroup.
+pub trait WaitGroupObserver: Send + Sync {
+    /// Count increased by `count` to `left`
+    fn on_add(&self, _count: usize, _left: usize) {}
+
+    /// Count decreased by `count` to `left`
+    fn on_done(&self, _count: usize, _left: usize) {}
+
+    /// A waiting future starts blocking, woken when the count is at or below `target`
+    fn on_wait_start(&self, _target: usize) {}
+
+    /// A blocking waiting future ends, by completion or cancellation, with `left` count
+    fn on_wait_end(&self, _left: usize) {}
+
+    /// Count decreased to zero
+    fn on_zero(&self) {}
+}
+
+static DEFAULT_OBSERVER: RwLock<Option<Arc<dyn WaitGroupObserver>>> = const_rwlock(None);
+
+/// Install the observer of every WaitGroup created afterwards without its own observer,
+/// including those created by dependencies. Remove it with None.
+///
+/// WaitGroups already created are not affected.
+pub fn set_default_observer(observer: Option<Arc<dyn WaitGroupObserver>>) {
+    *DEFAULT_OBSERVER.write() = observer;
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ObserverSlot(pub(crate) Option<Arc<dyn WaitGroupObserver>>);
+
+impl fmt::Debug for ObserverSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+impl ObserverSlot {
+    /// Fall back to the default observer
+    #[inline]
+    pub(crate) fn or_default(self) -> Self {
+        match self.0 {
+            Some(_) => self,
+            None => Self(DEFAULT_OBSERVER.read().clone()),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+/// How a wait went, returned by [crate::GenericWaitGroup::wait()] and
+/// [crate::GenericWaitGroup::wait_to()], for latency diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WaitOutcome {
+    /// The target was not reached on the first check, the waiting actually happened
+    pub blocked: bool,
+    /// Times the future was polled
+    pub polls: u32,
+    /// From the first check to the completion, zero if not blocked
+    pub elapsed: Duration,
+    /// The count that satisfied the target, lower than the target if several done() raced
+    pub left: usize,
+}
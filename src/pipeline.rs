@@ -0,0 +1,153 @@
+use std::{future::Future, sync::Arc};
+
+use crate::{WaitGroup, WaitGroupGuard, WaitOutcome};
+
+/// An ordered chain of WaitGroups, one per stage, for draining multi-stage processing stage
+/// by stage.
+///
+/// A unit enters the first stage, and is moved to the next one by advance(), it is counted by
+/// one stage at any time, see [crate::GenericWaitGroupGuard::handoff()].
+///
+/// # NOTE
+///
+/// * Like WaitGroup, only one wait_stage() per stage at the same time, and one wait_all() at
+///   the same time.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::Pipeline;
+/// use tokio::runtime::Runtime;
+///
+/// let pipeline = Pipeline::new(2);
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let guard = pipeline.enter();
+///     let _pipeline = pipeline.clone();
+///     tokio::spawn(async move {
+///         // Decode
+///         let guard = _pipeline.advance(guard).unwrap();
+///         // Store
+///         assert!(_pipeline.advance(guard).is_none());
+///     });
+///     pipeline.wait_stage(0).await;
+///     pipeline.wait_all().await;
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Pipeline {
+    stages: Arc<[WaitGroup]>,
+    all: WaitGroup,
+}
+
+/// A unit in a stage of a [Pipeline], decreases the count of the stage on drop.
+#[must_use = "dropping the guard decreases the count"]
+pub struct StageGuard {
+    guard: WaitGroupGuard,
+    stage: usize,
+    // Dropped after the stage, so wait_all() returns after every stage is drained
+    _all: WaitGroupGuard,
+}
+
+impl Pipeline {
+    /// # Panics
+    ///
+    /// If `stages` is zero.
+    pub fn new(stages: usize) -> Self {
+        assert!(stages > 0, "Pipeline needs at least one stage");
+        Self {
+            stages: (0..stages).map(|_| WaitGroup::new()).collect(),
+            all: WaitGroup::new(),
+        }
+    }
+
+    /// Count of stages
+    #[inline]
+    pub fn stage_count(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// The WaitGroup of stage `i`.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of range.
+    #[inline]
+    pub fn stage(&self, i: usize) -> &WaitGroup {
+        &self.stages[i]
+    }
+
+    /// Add a unit to the first stage.
+    #[inline]
+    #[track_caller]
+    pub fn enter(&self) -> StageGuard {
+        let _all = self.all.add_guard();
+        StageGuard {
+            guard: self.stages[0].add_guard(),
+            stage: 0,
+            _all,
+        }
+    }
+
+    /// Move the unit to the next stage, or complete it after the last stage and return None.
+    #[inline]
+    #[track_caller]
+    pub fn advance(&self, guard: StageGuard) -> Option<StageGuard> {
+        let next = guard.stage + 1;
+        if next == self.stages.len() {
+            return None;
+        }
+        let StageGuard { guard, _all, .. } = guard;
+        // Stages panic on a rejected add, Err never returns
+        let Ok(guard) = guard.handoff(&self.stages[next]) else {
+            unreachable!("Pipeline stage rejected the add");
+        };
+        Some(StageGuard {
+            guard,
+            stage: next,
+            _all,
+        })
+    }
+
+    /// Count left in every stage, in order
+    pub fn left(&self) -> Vec<usize> {
+        self.stages.iter().map(|wg| wg.left()).collect()
+    }
+
+    /// Total count of all stages
+    #[inline]
+    pub fn left_all(&self) -> usize {
+        self.all.left()
+    }
+
+    /// Wait until stage `i` drops to zero, while the others continue.
+    ///
+    /// Canceling future is supported.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of range.
+    #[inline]
+    #[track_caller]
+    pub fn wait_stage(&self, i: usize) -> impl Future<Output = WaitOutcome> + '_ {
+        self.stages[i].wait()
+    }
+
+    /// Wait until every stage drops to zero.
+    ///
+    /// Canceling future is supported.
+    #[inline]
+    #[track_caller]
+    pub fn wait_all(&self) -> impl Future<Output = WaitOutcome> + '_ {
+        self.all.wait()
+    }
+}
+
+impl StageGuard {
+    /// Index of the stage the unit is in
+    #[inline]
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use indicatif::ProgressBar;
+
+use crate::listener::{Listener, ListenerHandle};
+
+/// Registration created by [crate::WaitGroup::progress_bar()], stops updating the bar on drop.
+///
+/// The bar is not finished on drop, call finish() on it after the wait.
+#[must_use = "dropping the registration stops updating the bar"]
+pub struct ProgressBarRegistration {
+    _handle: ListenerHandle,
+    bar: ProgressBar,
+}
+
+impl ProgressBarRegistration {
+    /// The bar driven by the registration
+    #[inline]
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+}
+
+struct ProgressListener(ProgressBar);
+
+impl Listener for ProgressListener {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        let bar = &self.0;
+        bar.inc(old - left);
+        // Added since registered
+        let total = bar.position() + left;
+        if bar.length().is_some_and(|len| total > len) {
+            bar.set_length(total);
+        }
+    }
+}
+
+pub(crate) fn register(wg: &crate::WaitGroup, bar: ProgressBar) -> ProgressBarRegistration {
+    let listener = Arc::new(ProgressListener(bar.clone()));
+    let _handle = ListenerHandle::register(wg.0.clone(), listener);
+    let left = wg.0.left.load(Ordering::SeqCst);
+    bar.set_position(0);
+    bar.set_length(left);
+    ProgressBarRegistration { _handle, bar }
+}
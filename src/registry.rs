@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{WaitGroup, WaitGroupGuard};
+
+/// Record of a guard registered with the `diagnostics` feature.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct GuardInfo {
+    /// Unique within the WaitGroup
+    pub id: u64,
+    /// When the guard was added
+    pub added_at: Instant,
+    /// Name of the task
+    pub name: Option<String>,
+    /// Opaque value given by the user, eg. a request id
+    pub user_data: u64,
+    /// Expected completion time, for [WaitGroup::overdue()]
+    pub deadline: Option<Instant>,
+    /// Call site of the add
+    pub location: &'static Location<'static>,
+}
+
+/// A guard registered in its WaitGroup until dropped, created by
+/// [WaitGroup::add_guard_with_info()] or [WaitGroup::add_guard_with_deadline()].
+pub struct TaggedGuard {
+    // Dropped after the record is removed
+    _guard: WaitGroupGuard,
+    id: u64,
+}
+
+impl TaggedGuard {
+    /// The id of its [GuardInfo]
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for TaggedGuard {
+    #[inline]
+    fn drop(&mut self) {
+        self._guard.inner.registry.guards.lock().remove(&self.id);
+    }
+}
+
+/// Guards registered in a WaitGroup
+pub(crate) struct Registry {
+    next_id: AtomicU64,
+    guards: Mutex<HashMap<u64, GuardInfo>>,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            guards: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Guards matching `filter`, ordered by id
+    pub(crate) fn collect<F: Fn(&GuardInfo) -> bool>(&self, filter: F) -> Vec<GuardInfo> {
+        let mut guards: Vec<GuardInfo> = self
+            .guards
+            .lock()
+            .values()
+            .filter(|info| filter(info))
+            .cloned()
+            .collect();
+        guards.sort_by_key(|info| info.id);
+        guards
+    }
+
+    #[inline]
+    pub(crate) fn oldest(&self) -> Option<Instant> {
+        self.guards.lock().values().map(|info| info.added_at).min()
+    }
+}
+
+#[track_caller]
+pub(crate) fn add_guard(
+    wg: &WaitGroup,
+    name: Option<String>,
+    user_data: u64,
+    deadline: Option<Duration>,
+) -> TaggedGuard {
+    let location = Location::caller();
+    let guard = wg.add_guard();
+    let registry = &wg.0.registry;
+    let id = registry.next_id.fetch_add(1, Ordering::Relaxed);
+    let added_at = Instant::now();
+    registry.guards.lock().insert(
+        id,
+        GuardInfo {
+            id,
+            added_at,
+            name,
+            user_data,
+            deadline: deadline.map(|d| added_at + d),
+            location,
+        },
+    );
+    TaggedGuard { _guard: guard, id }
+}
@@ -0,0 +1,77 @@
+use std::thread;
+
+use crate::{WaitGroup, WaitGroupGuard};
+
+/// A scope which blocks the current thread on drop, until all the guards issued from it are
+/// done.
+///
+/// Tracked work never outlives the scope, including early returns and unwinding from panics.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::BlockingScope;
+/// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+///
+/// let count = Arc::new(AtomicUsize::new(0));
+/// {
+///     let scope = BlockingScope::new();
+///     for _ in 0..4 {
+///         let count = count.clone();
+///         scope.spawn(move || {
+///             count.fetch_add(1, Ordering::SeqCst);
+///         });
+///     }
+/// }
+/// assert_eq!(count.load(Ordering::SeqCst), 4);
+/// ```
+#[must_use = "the scope blocks on drop, dropping it immediately does not wait for later work"]
+pub struct BlockingScope {
+    wg: WaitGroup,
+}
+
+impl BlockingScope {
+    pub fn new() -> Self {
+        Self {
+            wg: WaitGroup::new(),
+        }
+    }
+
+    /// Return a guard which the scope waits for on drop.
+    #[inline]
+    pub fn guard(&self) -> WaitGroupGuard {
+        self.wg.add_guard()
+    }
+
+    /// Spawn a thread which the scope waits for on drop.
+    pub fn spawn<F, T>(&self, f: F) -> thread::JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let guard = self.guard();
+        thread::spawn(move || {
+            let _guard = guard;
+            f()
+        })
+    }
+
+    /// The underlying WaitGroup
+    #[inline]
+    pub fn wait_group(&self) -> &WaitGroup {
+        &self.wg
+    }
+}
+
+impl Default for BlockingScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for BlockingScope {
+    fn drop(&mut self) {
+        self.wg.wait_blocking();
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+
+use crate::{error::Elapsed, WaitGroup};
+
+type StageFn<'a> = Box<dyn FnMut(&WaitGroup) + Send + 'a>;
+
+/// Escalation policy for draining a WaitGroup, created by [WaitGroup::shutdown_plan()].
+///
+/// Each stage runs its callback once if the count has not reached zero at its offset from
+/// run(), eg. log the stragglers, then abort their tasks. The drain fails at the offset of
+/// fail_at(), or waits without limit if not set.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::WaitGroup;
+/// use std::time::Duration;
+/// use tokio::runtime::Runtime;
+///
+/// let wg = WaitGroup::new();
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let _guard = wg.add_guard();
+///     let r = wg
+///         .shutdown_plan()
+///         .stage(Duration::from_millis(5), |wg| {
+///             println!("{} tasks left", wg.left())
+///         })
+///         .stage(Duration::from_millis(10), |_wg| {
+///             // Abort the tasks
+///         })
+///         .fail_at(Duration::from_millis(20))
+///         .run()
+///         .await;
+///     assert!(r.is_err());
+/// });
+/// ```
+pub struct ShutdownPlan<'a> {
+    wg: &'a WaitGroup,
+    stages: Vec<(Duration, StageFn<'a>)>,
+    fail_at: Option<Duration>,
+}
+
+impl<'a> ShutdownPlan<'a> {
+    #[inline]
+    pub(crate) fn new(wg: &'a WaitGroup) -> Self {
+        Self {
+            wg,
+            stages: Vec::new(),
+            fail_at: None,
+        }
+    }
+
+    /// Call `f` if the count is not zero `at` after run() started.
+    ///
+    /// Stages run in the order of `at`, those added with the same `at` in the order added.
+    pub fn stage<F>(mut self, at: Duration, f: F) -> Self
+    where
+        F: FnMut(&WaitGroup) + Send + 'a,
+    {
+        self.stages.push((at, Box::new(f)));
+        self
+    }
+
+    /// Give up with Err(Elapsed) `at` after run() started, after the stages due by then.
+    pub fn fail_at(mut self, at: Duration) -> Self {
+        self.fail_at = Some(at);
+        self
+    }
+
+    /// Wait until zero count in the WaitGroup, escalating through the stages.
+    ///
+    /// Canceling future is supported, the stages not reached are not run.
+    pub async fn run(mut self) -> Result<(), Elapsed> {
+        let start = Instant::now();
+        // Stable, keeps the order added for the same offset
+        self.stages.sort_by_key(|(at, _)| *at);
+        for (at, f) in self.stages.iter_mut() {
+            if self.fail_at.is_some_and(|fail_at| fail_at < *at) {
+                break;
+            }
+            if self.wg.wait_to_deadline(0, start + *at).await.is_ok() {
+                return Ok(());
+            }
+            f(self.wg);
+        }
+        match self.fail_at {
+            Some(fail_at) => self
+                .wg
+                .wait_to_deadline(0, start + fail_at)
+                .await
+                .map(|_| ()),
+            None => {
+                self.wg.wait().await;
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::{Duration, Instant};
+
+/// Totals of a WaitGroup at a point of time, taken by [crate::WaitGroup::snapshot()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountSnapshot {
+    /// Total count increased since creation
+    pub added: u64,
+    /// Total count decreased since creation
+    pub completed: u64,
+    pub at: Instant,
+}
+
+/// Totals between two snapshots, returned by [crate::WaitGroup::delta_since()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountDelta {
+    pub added: u64,
+    pub completed: u64,
+    pub elapsed: Duration,
+}
+
+impl CountSnapshot {
+    /// Totals from `earlier` to this snapshot.
+    #[inline]
+    pub fn delta_since(&self, earlier: &CountSnapshot) -> CountDelta {
+        CountDelta {
+            added: self.added.wrapping_sub(earlier.added),
+            completed: self.completed.wrapping_sub(earlier.completed),
+            elapsed: self.at.saturating_duration_since(earlier.at),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Clock for timing waits, quanta with the `quanta` feature to keep the overhead low
+#[cfg(feature = "quanta")]
+pub(crate) use quanta::Instant;
+#[cfg(not(feature = "quanta"))]
+pub(crate) use std::time::Instant;
+
+/// Weight of the newest sample in the moving average
+const ALPHA: f64 = 0.125;
+
+/// Statistics of a WaitGroup, returned by [crate::WaitGroup::stats()].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    /// Count left at this moment
+    pub left: usize,
+    /// Exponentially weighted moving average of the count, updated on add() and done()
+    pub outstanding_avg: f64,
+    /// Total count increased since creation
+    pub added: u64,
+    /// Total count decreased since creation
+    pub completed: u64,
+    /// Waits returned after the count reached the target, waits not blocked excluded
+    pub waits_completed: u64,
+    /// Waits given up before the count reached the target, by dropping the future or timeout.
+    /// Growing against waits_completed hints the timeouts are too short for the drain.
+    pub waits_canceled: u64,
+    /// Wakes by done() that found the waker lock held, by a registering waiter or another
+    /// done()
+    pub done_contended: u64,
+    /// Registrations, updates and cancels of a wait that found the waker lock held
+    pub register_contended: u64,
+}
+
+/// Moving average of the count, stored as f64 bits
+pub(crate) struct Gauge(AtomicU64);
+
+impl Gauge {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    #[inline]
+    pub(crate) fn update(&self, left: u64) {
+        let sample = left as f64;
+        let _ = self
+            .0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                let avg = f64::from_bits(bits);
+                Some((avg + ALPHA * (sample - avg)).to_bits())
+            });
+    }
+
+    #[inline]
+    pub(crate) fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Acquire))
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    listener::{Listener, ListenerHandle},
+    WaitGroup,
+};
+
+/// Send the count of a WaitGroup to a statsd endpoint over UDP, started by
+/// [WaitGroup::export_statsd()].
+///
+/// Two metrics are sent:
+///
+/// * `<prefix>.outstanding`, a gauge of the count left
+///
+/// * `<prefix>.completed`, a counter of the count decreased
+///
+/// By default the metrics are sent on every done(), or sampled on an interval with
+/// [StatsdExporter::with_interval()] for busy WaitGroups. Sending is best effort, errors are
+/// ignored.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{StatsdExporter, WaitGroup};
+/// use std::time::Duration;
+///
+/// let wg = WaitGroup::new();
+/// let exporter = StatsdExporter::new("127.0.0.1:8125", "jobs")
+///     .unwrap()
+///     .with_tag("env", "test")
+///     .with_interval(Duration::from_secs(10));
+/// let _registration = wg.export_statsd(exporter);
+/// ```
+pub struct StatsdExporter {
+    socket: UdpSocket,
+    prefix: String,
+    /// Suffix of dogstatsd tags, empty without tags
+    tags: String,
+    interval: Option<Duration>,
+}
+
+impl StatsdExporter {
+    /// Send to `addr` with metric names starting with `prefix`.
+    pub fn new<A: ToSocketAddrs>(addr: A, prefix: &str) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(addr)?;
+        // Never block done()
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+            tags: String::new(),
+            interval: None,
+        })
+    }
+
+    /// Add a dogstatsd tag to every metric.
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        if self.tags.is_empty() {
+            self.tags.push_str("|#");
+        } else {
+            self.tags.push(',');
+        }
+        self.tags.push_str(key);
+        self.tags.push(':');
+        self.tags.push_str(value);
+        self
+    }
+
+    /// Sample the metrics every `interval` on a background thread, instead of every done().
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    #[inline]
+    fn send(&self, left: u64, completed: u64) {
+        let mut packet = format!("{}.outstanding:{}|g{}", self.prefix, left, self.tags);
+        if completed > 0 {
+            packet.push_str(&format!(
+                "\n{}.completed:{}|c{}",
+                self.prefix, completed, self.tags
+            ));
+        }
+        let _ = self.socket.send(packet.as_bytes());
+    }
+}
+
+impl Listener for StatsdExporter {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        self.send(left, old - left);
+    }
+}
+
+/// Registration created by [WaitGroup::export_statsd()], stops sending on drop.
+#[must_use = "dropping the registration stops the export"]
+pub struct StatsdRegistration {
+    _handle: Option<ListenerHandle>,
+    sampler: Option<Sampler>,
+}
+
+struct Sampler {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    th: thread::JoinHandle<()>,
+}
+
+impl Drop for StatsdRegistration {
+    fn drop(&mut self) {
+        if let Some(sampler) = self.sampler.take() {
+            *sampler.stop.0.lock() = true;
+            sampler.stop.1.notify_one();
+            let _ = sampler.th.join();
+        }
+    }
+}
+
+pub(crate) fn export(wg: &WaitGroup, exporter: StatsdExporter) -> StatsdRegistration {
+    let Some(interval) = exporter.interval else {
+        exporter.send(wg.left() as u64, 0);
+        let _handle = ListenerHandle::register(wg.0.clone(), Arc::new(exporter));
+        return StatsdRegistration {
+            _handle: Some(_handle),
+            sampler: None,
+        };
+    };
+    let stop = Arc::new((Mutex::new(false), Condvar::new()));
+    let _stop = stop.clone();
+    let wg = wg.clone();
+    let th = thread::Builder::new()
+        .name("waitgroup-statsd".to_string())
+        .spawn(move || {
+            let (lock, cond) = &*_stop;
+            let mut last_completed = wg.completed();
+            let mut stopped = lock.lock();
+            loop {
+                let completed = wg.completed();
+                exporter.send(wg.left() as u64, completed - last_completed);
+                last_completed = completed;
+                cond.wait_for(&mut stopped, interval);
+                if *stopped {
+                    return;
+                }
+            }
+        })
+        .expect("spawn statsd exporter");
+    StatsdRegistration {
+        _handle: None,
+        sampler: Some(Sampler { stop, th }),
+    }
+}
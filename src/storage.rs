@@ -0,0 +1,85 @@
+use std::{panic::Location, task::Waker};
+
+/// Storage behind a [crate::GenericWaitGroup]: `Arc` of atomics for [crate::WaitGroup], or
+/// `Rc` of cells for [crate::LocalWaitGroup].
+///
+/// Sealed, it is not meant to be implemented outside this crate.
+pub trait Storage: Clone + sealed::Sealed {
+    #[doc(hidden)]
+    type Raw: RawWaitGroup;
+
+    #[doc(hidden)]
+    fn create() -> Self;
+
+    #[doc(hidden)]
+    fn raw(&self) -> &Self::Raw;
+
+    /// A new storage with the options of this one
+    #[doc(hidden)]
+    #[inline(always)]
+    fn create_like(&self) -> Self {
+        Self::create()
+    }
+
+    /// A handle (not guard) is cloned
+    #[doc(hidden)]
+    #[inline(always)]
+    fn clone_handle(&self) {}
+
+    /// A handle (not guard) is dropped
+    #[doc(hidden)]
+    #[inline(always)]
+    fn drop_handle(&self) {}
+}
+
+/// No waiter in the slot
+pub(crate) const NO_WAITER: u64 = u64::MAX;
+
+/// Threshold for a waiter woken on every done()
+pub(crate) const WAKE_ALWAYS: u64 = u64::MAX - 1;
+
+/// The counter with a single waiter slot
+#[doc(hidden)]
+pub trait RawWaitGroup {
+    /// For the error message
+    const NAME: &'static str;
+
+    fn load_left(&self) -> u64;
+
+    /// Return false if the count is not changed, eg. closed, the error is reported
+    #[track_caller]
+    fn add(&self, count: usize) -> bool;
+
+    /// Return false if the count is not changed, eg. underflow, the error is reported
+    #[track_caller]
+    fn done(&self, count: usize) -> bool;
+
+    /// Waking is deferred by freeze()
+    #[inline(always)]
+    fn is_frozen(&self) -> bool {
+        false
+    }
+
+    /// done() by guard drop
+    #[inline(always)]
+    fn guard_done(&self) {
+        self.done(1);
+    }
+
+    /// Once waker set, waker might be false waken many times
+    /// `caller` is the location of the wait, for the error message
+    /// Returns: waker_id
+    fn set_waker(&self, waker: Waker, wake_at: u64, caller: &'static Location<'static>) -> u64;
+
+    /// Replace the registered waker, if it is still `waker_id` and would not wake the same task,
+    /// eg. the future moved to another task.
+    /// Returns false if `waker_id` is stale, eg. dropped by reset(), to register again.
+    fn update_waker(&self, waker_id: u64, waker: &Waker) -> bool;
+
+    /// `satisfied` is false if the wait is given up, eg. the future dropped or timed out
+    fn cancel_wait(&self, waker_id: u64, satisfied: bool);
+}
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
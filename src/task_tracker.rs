@@ -0,0 +1,24 @@
+use tokio_util::task::{task_tracker::TaskTrackerToken, TaskTracker};
+
+use crate::WaitGroupGuard;
+
+/// A unit counted by both a WaitGroup and a TaskTracker, created by
+/// [crate::WaitGroup::add_guard_with_tracker()], released from both on drop.
+///
+/// During a migration, work started by the code moved to one side is waited for by the code
+/// still on the other side.
+#[must_use = "dropping the guard decreases the count"]
+pub struct TrackerGuard {
+    _guard: WaitGroupGuard,
+    _token: TaskTrackerToken,
+}
+
+impl TrackerGuard {
+    #[inline]
+    pub(crate) fn new(guard: WaitGroupGuard, tracker: &TaskTracker) -> Self {
+        Self {
+            _guard: guard,
+            _token: tracker.token(),
+        }
+    }
+}
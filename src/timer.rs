@@ -0,0 +1,100 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use crate::Elapsed;
+
+/// Timer backend for the timeouts of wait_to_timeout(), so that they work on any
+/// executor which provides a sleep future.
+///
+/// # NOTE
+///
+/// * No adapter is shipped for thread-per-core runtimes such as monoio or glommio: implement
+///   Timer over their sleep future, and spawn their local tasks with track().
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::Timer;
+/// use std::time::Instant;
+///
+/// struct TokioTimer;
+///
+/// impl Timer for TokioTimer {
+///     type Sleep = tokio::time::Sleep;
+///
+///     fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+///         tokio::time::sleep_until(deadline.into())
+///     }
+/// }
+/// ```
+pub trait Timer {
+    type Sleep: Future<Output = ()>;
+
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep;
+}
+
+/// [Timer] backed by futures-timer, with its own timer thread, independent of the executor.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::{FuturesTimer, WaitGroup};
+/// use std::time::Duration;
+///
+/// let wg = WaitGroup::new();
+/// let _guard = wg.add_guard();
+/// futures::executor::block_on(async {
+///     let r = wg.wait_to_timeout(0, Duration::from_millis(10), &FuturesTimer).await;
+///     assert!(r.is_err());
+/// });
+/// ```
+#[cfg(feature = "futures-timer")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuturesTimer;
+
+#[cfg(feature = "futures-timer")]
+impl Timer for FuturesTimer {
+    type Sleep = futures_timer::Delay;
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+        futures_timer::Delay::new(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+/// Race the future against the timer
+pub(crate) struct Timeout<F, S> {
+    f: F,
+    sleep: S,
+}
+
+impl<F, S> Timeout<F, S> {
+    #[inline]
+    pub(crate) fn new(f: F, sleep: S) -> Self {
+        Self { f, sleep }
+    }
+}
+
+impl<F: Future, S: Future<Output = ()>> Future for Timeout<F, S> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        // Safety: the fields are structurally pinned, and never moved out
+        let _self = unsafe { self.get_unchecked_mut() };
+        let f = unsafe { Pin::new_unchecked(&mut _self.f) };
+        if let Poll::Ready(r) = f.poll(ctx) {
+            return Poll::Ready(Ok(r));
+        }
+        let sleep = unsafe { Pin::new_unchecked(&mut _self.sleep) };
+        if sleep.poll(ctx).is_ready() {
+            return Poll::Ready(Err(Elapsed));
+        }
+        Poll::Pending
+    }
+}
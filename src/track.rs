@@ -0,0 +1,40 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A future holding a guard until completion, created by track().
+///
+/// Dropping the future before completion also drops the guard.
+pub struct Tracked<F, G> {
+    future: F,
+    guard: Option<G>,
+}
+
+impl<F, G> Tracked<F, G> {
+    #[inline]
+    pub(crate) fn new(future: F, guard: G) -> Self {
+        Self {
+            future,
+            guard: Some(guard),
+        }
+    }
+}
+
+impl<F: Future, G> Future for Tracked<F, G> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        // Safety: the future is structurally pinned and never moved, the guard is not pinned
+        let _self = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut _self.future) };
+        match future.poll(ctx) {
+            Poll::Ready(r) => {
+                _self.guard.take();
+                Poll::Ready(r)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    future::Future,
+    panic::Location,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{storage::RawWaitGroup, Storage, WaitGroupFuture, WaitOutcome};
+
+/// Wait until the target count is left, returned by [crate::GenericWaitGroup::wait()],
+/// [crate::GenericWaitGroup::wait_to()] and [crate::GenericWaitGroup::waiter()].
+///
+/// Enabled before the work is spawned, the waiter is registered before any done() can
+/// happen, instead of relying on the recheck of the first poll.
+///
+/// Always Unpin, it can be stored in a struct and polled without `Box::pin`. Implements
+/// `FusedFuture` with the `futures-core` feature, for `futures::select!`.
+///
+/// # NOTE
+///
+/// * Only one waiting future at the same time, an enabled waiter counts as one.
+///
+/// * Polling it again after completion panics.
+///
+/// * Dropping the waiter cancels the registration.
+///
+/// # Example
+///
+/// ```
+/// extern crate atomic_waitgroup;
+/// use atomic_waitgroup::WaitGroup;
+/// use tokio::runtime::Runtime;
+///
+/// let wg = WaitGroup::new();
+/// let rt = Runtime::new().unwrap();
+/// rt.block_on(async move {
+///     let mut waiter = wg.waiter();
+///     let guards: Vec<_> = (0..3).map(|_| wg.add_guard()).collect();
+///     waiter.enable();
+///     tokio::spawn(async move {
+///         drop(guards);
+///     });
+///     waiter.await;
+/// });
+/// ```
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Waiter<'a, S: Storage> {
+    f: WaitGroupFuture<'a, S::Raw>,
+    polls: u32,
+    /// Not satisfied on the first check, by enable() registering or the first poll
+    blocked: Option<bool>,
+    /// When found not satisfied
+    start: Option<Instant>,
+    terminated: bool,
+}
+
+impl<'a, S: Storage> Waiter<'a, S> {
+    #[inline]
+    pub(crate) fn new(wg: &'a S, target: usize, caller: &'static Location<'static>) -> Self {
+        Self {
+            f: WaitGroupFuture {
+                wg: wg.raw(),
+                target,
+                waker_id: 0,
+                caller,
+            },
+            polls: 0,
+            blocked: None,
+            start: None,
+            terminated: false,
+        }
+    }
+
+    /// Register the waiter now, unless the target is already reached, the task is woken
+    /// once polled. Calling it again once registered, or after a poll, does nothing.
+    ///
+    /// If the target is reached, nothing is registered and the first poll checks again, an
+    /// add() in between makes it wait.
+    ///
+    /// # Panics
+    ///
+    /// If another future is waiting on the WaitGroup.
+    pub fn enable(&mut self) {
+        if self.blocked.is_some() {
+            return;
+        }
+        let f = &mut self.f;
+        if f.wg.load_left() <= f.target as u64 && !f.wg.is_frozen() {
+            // Left to the first poll
+            return;
+        }
+        self.blocked = Some(true);
+        self.start = Some(Instant::now());
+        // Replaced by the waker of the first poll
+        f.waker_id =
+            f.wg.set_waker(Waker::noop().clone(), f.target as u64, f.caller);
+    }
+}
+
+impl<S: Storage> Future for Waiter<'_, S> {
+    type Output = WaitOutcome;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let _self = self.get_mut();
+        assert!(!_self.terminated, "Waiter polled after completion");
+        _self.polls = _self.polls.saturating_add(1);
+        match Pin::new(&mut _self.f).poll(ctx) {
+            Poll::Ready(left) => {
+                _self.terminated = true;
+                let blocked = *_self.blocked.get_or_insert(false);
+                Poll::Ready(WaitOutcome {
+                    blocked,
+                    polls: _self.polls,
+                    elapsed: _self.start.map_or(Duration::ZERO, |start| start.elapsed()),
+                    left: left as usize,
+                })
+            }
+            Poll::Pending => {
+                if _self.blocked.is_none() {
+                    _self.blocked = Some(true);
+                    _self.start = Some(Instant::now());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<S: Storage> futures_core::future::FusedFuture for Waiter<'_, S> {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
@@ -0,0 +1,65 @@
+use std::task::Waker;
+
+#[cfg(feature = "critical-section")]
+use std::cell::RefCell;
+
+/// Storage of the waiter's waker.
+///
+/// Locked by parking_lot by default. With the `critical-section` feature, the waker is
+/// accessed inside a critical section instead, so that interrupt-driven executors can
+/// register and wake safely (the platform provides the critical-section implementation).
+///
+/// Neither lock is poisoned by a panic, a waiter task panicking while registered does not
+/// leave the WaitGroup unusable for the others.
+#[cfg(not(feature = "critical-section"))]
+pub(crate) struct WakerSlot(parking_lot::Mutex<Option<Waker>>);
+
+#[cfg(feature = "critical-section")]
+pub(crate) struct WakerSlot(critical_section::Mutex<RefCell<Option<Waker>>>);
+
+impl WakerSlot {
+    #[cfg(not(feature = "critical-section"))]
+    #[inline(always)]
+    pub(crate) const fn new() -> Self {
+        Self(parking_lot::Mutex::new(None))
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[inline(always)]
+    pub(crate) const fn new() -> Self {
+        Self(critical_section::Mutex::new(RefCell::new(None)))
+    }
+
+    /// Do not panic inside `f`
+    #[cfg(not(feature = "critical-section"))]
+    #[inline(always)]
+    pub(crate) fn with<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, f: F) -> R {
+        f(&mut self.0.lock())
+    }
+
+    /// Do not panic inside `f`
+    #[cfg(feature = "critical-section")]
+    #[inline(always)]
+    pub(crate) fn with<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, f: F) -> R {
+        critical_section::with(|cs| f(&mut self.0.borrow_ref_mut(cs)))
+    }
+}
+
+impl WakerSlot {
+    /// Never blocks, return None if the lock is contended
+    #[cfg(not(feature = "critical-section"))]
+    #[inline(always)]
+    pub(crate) fn try_with<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, f: F) -> Option<R> {
+        self.0.try_lock().map(|mut guard| f(&mut guard))
+    }
+
+    /// Return None if already borrowed by the same context (nested)
+    #[cfg(feature = "critical-section")]
+    #[inline(always)]
+    pub(crate) fn try_with<R, F: FnOnce(&mut Option<Waker>) -> R>(&self, f: F) -> Option<R> {
+        critical_section::with(|cs| {
+            let mut guard = self.0.borrow(cs).try_borrow_mut().ok()?;
+            Some(f(&mut guard))
+        })
+    }
+}
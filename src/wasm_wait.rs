@@ -0,0 +1,100 @@
+use std::sync::{
+    atomic::{AtomicI32, AtomicUsize, Ordering},
+    Arc,
+};
+
+use js_sys::{Atomics, Date, Int32Array, WebAssembly};
+use wasm_bindgen::JsCast;
+
+use crate::{error::Elapsed, listener::Listener, WaitGroupInner};
+
+/// Web workers blocked in wait_to_blocking(), woken by Atomics.notify.
+///
+/// Atomics.wait blocks on a word in the shared memory, which is a sequence increased on
+/// every done(), the count itself is 64 bits.
+pub(crate) struct WasmWaiters {
+    /// Count of blocked workers, to skip the notify in done()
+    count: AtomicUsize,
+    seq: AtomicI32,
+}
+
+impl Listener for WasmWaiters {
+    #[inline]
+    fn on_done(&self, _old: u64, _left: u64) {
+        self.seq.fetch_add(1, Ordering::SeqCst);
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let (memory, index) = self.view();
+        let _ = Atomics::notify(&memory, index);
+    }
+}
+
+impl WasmWaiters {
+    pub(crate) fn get(inner: &WaitGroupInner) -> &WasmWaiters {
+        inner.wasm.get_or_init(|| {
+            let waiters = Arc::new(WasmWaiters {
+                count: AtomicUsize::new(0),
+                seq: AtomicI32::new(0),
+            });
+            // Registered for the lifetime of the WaitGroup
+            inner.listeners.write().push(waiters.clone());
+            inner.has_listeners.store(true, Ordering::SeqCst);
+            waiters
+        })
+    }
+
+    /// The shared memory as Int32Array, and the index of the sequence in it.
+    /// The view is taken every time, the buffer is replaced when the memory grows.
+    #[inline]
+    fn view(&self) -> (Int32Array, u32) {
+        let memory = wasm_bindgen::memory().unchecked_into::<WebAssembly::Memory>();
+        let index = &self.seq as *const AtomicI32 as u32 / 4;
+        (Int32Array::new(&memory.buffer()), index)
+    }
+
+    /// `deadline` in milliseconds of Date.now().
+    /// Return Ok(true) if blocked
+    ///
+    /// # Panics
+    ///
+    /// On the main thread of the browser, where Atomics.wait is not allowed.
+    pub(crate) fn wait(
+        &self,
+        inner: &WaitGroupInner,
+        target: u64,
+        deadline: Option<f64>,
+    ) -> Result<bool, Elapsed> {
+        if inner.released(target) {
+            return Ok(false);
+        }
+        self.count.fetch_add(1, Ordering::SeqCst);
+        let mut r = Ok(true);
+        loop {
+            // done() increases the sequence before the notify, a change after this load
+            // returns Atomics.wait immediately
+            let seq = self.seq.load(Ordering::SeqCst);
+            if inner.released(target) {
+                break;
+            }
+            let (memory, index) = self.view();
+            let waited = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline - Date::now();
+                    if timeout <= 0.0 {
+                        r = Err(Elapsed);
+                        break;
+                    }
+                    Atomics::wait_with_timeout(&memory, index, seq, timeout)
+                }
+                None => Atomics::wait(&memory, index, seq),
+            };
+            if waited.is_err() {
+                self.count.fetch_sub(1, Ordering::SeqCst);
+                panic!("WaitGroup blocking wait is not allowed on the main thread of the browser");
+            }
+        }
+        self.count.fetch_sub(1, Ordering::SeqCst);
+        r
+    }
+}
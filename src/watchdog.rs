@@ -0,0 +1,86 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use parking_lot::{Condvar, Mutex};
+
+use crate::WaitGroup;
+
+/// Handle of a running watchdog, created by [WaitGroup::watchdog()] or
+/// [WaitGroup::watchdog_with()].
+///
+/// The watchdog thread is stopped when this handle is dropped.
+pub struct Watchdog {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    th: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn spawn<F>(wg: WaitGroup, bound: Duration, mut on_stall: F) -> Self
+    where
+        F: FnMut(&WaitGroup) + Send + 'static,
+    {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let _stop = stop.clone();
+        // Check a few times within the bound, so the report is not late by a whole bound
+        let interval = (bound / 4).max(Duration::from_millis(1));
+        let th = thread::Builder::new()
+            .name("waitgroup-watchdog".to_string())
+            .spawn(move || {
+                let (lock, cond) = &*_stop;
+                let mut last_completed = wg.completed();
+                let mut last_progress = Instant::now();
+                let mut fired = false;
+                let mut stopped = lock.lock();
+                loop {
+                    cond.wait_for(&mut stopped, interval);
+                    if *stopped {
+                        return;
+                    }
+                    let completed = wg.completed();
+                    if completed != last_completed || wg.left() == 0 {
+                        last_completed = completed;
+                        last_progress = Instant::now();
+                        fired = false;
+                        continue;
+                    }
+                    // Report once per stall
+                    if !fired && last_progress.elapsed() >= bound {
+                        fired = true;
+                        on_stall(&wg);
+                    }
+                }
+            })
+            .expect("spawn watchdog");
+        Self { stop, th: Some(th) }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        *self.stop.0.lock() = true;
+        self.stop.1.notify_one();
+        if let Some(th) = self.th.take() {
+            let _ = th.join();
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn log_stall(wg: &WaitGroup, bound: Duration) {
+    warn!("WaitGroup no progress for more than {:?}: {:?}", bound, wg);
+    #[cfg(feature = "diagnostics")]
+    for info in wg.outstanding() {
+        warn!(
+            "  outstanding guard #{} {:?} user_data={} added {:?} ago at {}",
+            info.id,
+            info.name,
+            info.user_data,
+            info.added_at.elapsed(),
+            info.location
+        );
+    }
+}
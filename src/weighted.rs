@@ -0,0 +1,32 @@
+use crate::{inner::SharedArc, WaitGroupInner};
+
+/// Decrease the count of its WaitGroup by its weight on drop, for a chunk of work admitted at
+/// once, created by [crate::WaitGroup::wait_to_and_add()] or
+/// [crate::WaitGroup::acquire_weighted()].
+#[must_use = "dropping the guard decreases the count"]
+pub struct WeightedGuard {
+    inner: SharedArc<WaitGroupInner>,
+    weight: usize,
+}
+
+impl WeightedGuard {
+    #[inline]
+    pub(crate) fn new(inner: SharedArc<WaitGroupInner>, weight: usize) -> Self {
+        Self { inner, weight }
+    }
+
+    /// The count added by the guard, zero if the add was rejected
+    #[inline]
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+}
+
+impl Drop for WeightedGuard {
+    #[inline]
+    fn drop(&mut self) {
+        if self.weight > 0 {
+            self.inner.guard_done_many(self.weight);
+        }
+    }
+}
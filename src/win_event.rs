@@ -0,0 +1,68 @@
+use std::{
+    marker::PhantomData,
+    os::windows::io::{AsRawHandle, BorrowedHandle},
+    sync::Arc,
+};
+
+use log::error;
+use windows_sys::Win32::System::Threading::SetEvent;
+
+use crate::listener::{Listener, ListenerHandle};
+
+/// Registration created by [crate::WaitGroup::register_event_handle()], stops signaling on
+/// drop.
+///
+/// Borrows the handle, so that it cannot be closed while registered.
+#[must_use = "dropping the registration stops the notification"]
+pub struct EventHandleRegistration<'h> {
+    _handle: ListenerHandle,
+    _event: PhantomData<BorrowedHandle<'h>>,
+}
+
+struct EventHandleListener {
+    // HANDLE is a raw pointer, kept as usize to be Send + Sync
+    event: usize,
+    threshold: u64,
+}
+
+impl EventHandleListener {
+    #[inline]
+    fn signal(&self) {
+        if unsafe { SetEvent(self.event as _) } == 0 {
+            error!(
+                "WaitGroup SetEvent {:#x} error: {}",
+                self.event,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+impl Listener for EventHandleListener {
+    #[inline]
+    fn on_done(&self, old: u64, left: u64) {
+        if old > self.threshold && left <= self.threshold {
+            self.signal();
+        }
+    }
+}
+
+pub(crate) fn register<'h>(
+    wg: &crate::WaitGroup,
+    event: BorrowedHandle<'h>,
+    threshold: usize,
+) -> EventHandleRegistration<'h> {
+    let listener = Arc::new(EventHandleListener {
+        event: event.as_raw_handle() as usize,
+        threshold: threshold as u64,
+    });
+    let _handle = ListenerHandle::register(wg.0.clone(), listener.clone());
+    // Registered before checking, might signal twice but never miss
+    if wg.0.left.load(std::sync::atomic::Ordering::SeqCst) <= listener.threshold {
+        listener.signal();
+    }
+    EventHandleRegistration {
+        _handle,
+        _event: PhantomData,
+    }
+}
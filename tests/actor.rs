@@ -0,0 +1,60 @@
+#![cfg(feature = "actix")]
+
+use actix::prelude::*;
+use atomic_waitgroup::actor::ActorTracker;
+use std::time::Duration;
+
+struct Worker(ActorTracker);
+
+impl Actor for Worker {
+    type Context = Context<Self>;
+}
+
+struct Work(u64);
+
+impl Message for Work {
+    type Result = ();
+}
+
+impl Handler<Work> for Worker {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: Work, _ctx: &mut Self::Context) -> Self::Result {
+        Box::pin(self.0.track_act(actix::fut::wrap_future(async move {
+            actix::clock::sleep(Duration::from_millis(msg.0)).await;
+        })))
+    }
+}
+
+struct Spawn(u64);
+
+impl Message for Spawn {
+    type Result = ();
+}
+
+impl Handler<Spawn> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, msg: Spawn, _ctx: &mut Self::Context) {
+        actix::spawn(self.0.track(async move {
+            actix::clock::sleep(Duration::from_millis(msg.0)).await;
+        }));
+    }
+}
+
+#[test]
+fn test_actor_stopped() {
+    System::new().block_on(async move {
+        let tracker = ActorTracker::new();
+        let addr = Worker(tracker.clone()).start();
+        for i in 1..5 {
+            addr.do_send(Work(i * 20));
+            addr.do_send(Spawn(i * 20));
+        }
+        // Messages are handled in order, after this all the work is tracked
+        addr.send(Spawn(0)).await.unwrap();
+        assert!(tracker.in_flight() > 0);
+        tracker.stopped().await;
+        assert_eq!(tracker.in_flight(), 0);
+    });
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "diagnostics")]
+
+use atomic_waitgroup::diagnostics::{to_chrome_trace, Op, Transition};
+use std::{
+    panic::Location,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn test_chrome_trace() {
+    assert_eq!(to_chrome_trace(&[]), "[]");
+    let start = Instant::now();
+    let main = std::thread::current().id();
+    let other = std::thread::spawn(|| std::thread::current().id())
+        .join()
+        .unwrap();
+    let transition = |seq: u64, thread, ms: u64, op, left| Transition {
+        seq,
+        thread,
+        at: start + Duration::from_millis(ms),
+        group: 0x1000,
+        op,
+        left,
+        location: Location::caller(),
+    };
+    let transitions = [
+        transition(0, main, 0, Op::Add(2), 2),
+        transition(
+            1,
+            main,
+            1,
+            Op::SetWaker {
+                waker_id: 1,
+                wake_at: 0,
+            },
+            2,
+        ),
+        transition(2, other, 2, Op::Done(2), 0),
+        transition(3, main, 3, Op::Cancel { waker_id: 1 }, 0),
+    ];
+    let json: serde_json::Value = serde_json::from_str(&to_chrome_trace(&transitions)).unwrap();
+    let events = json.as_array().unwrap();
+    // An event and a counter sample per transition
+    assert_eq!(events.len(), 8);
+    assert_eq!(events[0]["name"], "add 2");
+    assert_eq!(events[0]["ph"], "i");
+    assert_eq!(events[0]["tid"], 1);
+    assert_eq!(events[1]["ph"], "C");
+    assert_eq!(events[1]["args"]["left"], 2);
+    assert_eq!(events[2]["ph"], "b");
+    assert_eq!(events[4]["name"], "done 2");
+    assert_eq!(events[4]["tid"], 2);
+    assert_eq!(events[4]["ts"], 2000.0);
+    assert_eq!(events[6]["ph"], "e");
+    assert_eq!(events[6]["id"], events[2]["id"]);
+    assert!(events[0]["args"]["location"]
+        .as_str()
+        .unwrap()
+        .contains("chrome_trace.rs"));
+}
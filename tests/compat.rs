@@ -0,0 +1,51 @@
+#![cfg(feature = "compat")]
+
+use atomic_waitgroup::compat::{awaitgroup, waitgroup};
+use std::time::Duration;
+
+fn make_runtime(threads: usize) -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(threads)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_awaitgroup_compat() {
+    make_runtime(2).block_on(async {
+        let mut wg = awaitgroup::WaitGroup::new();
+        let worker = wg.worker();
+        for _ in 0..4 {
+            let worker = worker.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                worker.done();
+            });
+        }
+        assert_eq!(wg.inner().left(), 5);
+        worker.done();
+        wg.wait().await;
+        assert_eq!(wg.inner().left(), 0);
+    });
+}
+
+#[test]
+fn test_waitgroup_compat() {
+    make_runtime(2).block_on(async {
+        let wg = waitgroup::WaitGroup::new();
+        let worker = wg.worker();
+        for _ in 0..4 {
+            let worker = worker.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                drop(worker);
+            });
+        }
+        drop(worker);
+        let inner = wg.inner().clone();
+        wg.wait().await;
+        assert_eq!(inner.left(), 0);
+        assert_eq!(inner.completed(), 5);
+    });
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "diagnostics")]
+
+use atomic_waitgroup::{
+    diagnostics::{self, Op, Transition},
+    WaitGroup,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+
+static TRANSITIONS: Mutex<Vec<Transition>> = Mutex::new(Vec::new());
+
+fn record(transition: &Transition) {
+    TRANSITIONS.lock().unwrap().push(transition.clone());
+}
+
+#[test]
+fn test_diagnostics() {
+    diagnostics::set_hook(Some(record));
+    let wg = WaitGroup::new();
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        wg.add(2);
+        let _wg = wg.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            _wg.done();
+            _wg.done();
+        });
+        assert!(wg.wait_to(0).await.blocked);
+    });
+    diagnostics::set_hook(None);
+    let transitions = TRANSITIONS.lock().unwrap();
+    let ops: Vec<Op> = transitions.iter().map(|t| t.op).collect();
+    assert_eq!(ops[0], Op::Add(2));
+    assert!(matches!(ops[1], Op::SetWaker { wake_at: 0, .. }));
+    assert_eq!(&ops[2..4], &[Op::Done(1), Op::Done(1)]);
+    assert!(matches!(ops[4], Op::Cancel { .. }));
+    assert_eq!(transitions[3].left, 0);
+    assert_eq!(transitions[0].location.file(), file!());
+    assert_eq!(transitions[1].location.file(), file!());
+    assert_ne!(transitions[0].thread, transitions[2].thread);
+    for pair in transitions.windows(2) {
+        assert!(pair[0].seq < pair[1].seq);
+        assert_eq!(pair[0].group, pair[1].group);
+    }
+}
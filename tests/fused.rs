@@ -0,0 +1,32 @@
+#![cfg(feature = "futures-core")]
+
+use atomic_waitgroup::WaitGroup;
+use futures::{future::FusedFuture, select, FutureExt};
+use std::time::Duration;
+
+#[test]
+fn test_select_fused() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let wg = WaitGroup::new();
+        let guard = wg.add_guard();
+        let mut wait = wg.wait();
+        let mut tick = Box::pin(tokio::time::sleep(Duration::from_millis(10)).fuse());
+        let mut ticks = 0;
+        let mut guard = Some(guard);
+        loop {
+            select! {
+                outcome = wait => {
+                    assert!(outcome.blocked);
+                    break;
+                }
+                _ = tick => {
+                    ticks += 1;
+                    guard.take();
+                }
+            }
+        }
+        assert_eq!(ticks, 1);
+        assert!(wait.is_terminated());
+    });
+}
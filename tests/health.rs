@@ -0,0 +1,50 @@
+#![cfg(feature = "health")]
+
+use atomic_waitgroup::WaitGroup;
+use std::time::Duration;
+
+#[test]
+fn test_readiness() {
+    let wg = WaitGroup::new();
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let guard = wg.add_guard();
+        let readiness = wg.readiness();
+        assert!(readiness.is_ready());
+        assert_eq!(readiness.left, 1);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let readiness = _wg.readiness();
+            drop(guard);
+            readiness
+        });
+        wg.wait().await;
+        let readiness = th.await.unwrap();
+        assert!(readiness.draining);
+        assert_eq!(readiness.http_status(), 503);
+        let json: serde_json::Value = serde_json::from_str(&readiness.to_json()).unwrap();
+        assert_eq!(json["ready"], false);
+        assert_eq!(json["draining"], true);
+        assert_eq!(json["left"], 1);
+        assert!(wg.readiness().is_ready());
+    });
+}
+
+#[test]
+fn test_readiness_blocking() {
+    let wg = WaitGroup::new();
+    let guard = wg.add_guard();
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || _wg.wait_blocking());
+    while wg.readiness().is_ready() {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    drop(guard);
+    th.join().unwrap();
+    assert_eq!(wg.readiness().left, 0);
+}
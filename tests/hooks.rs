@@ -0,0 +1,60 @@
+#![cfg(feature = "test-hooks")]
+
+use atomic_waitgroup::{
+    hooks::{self, Point},
+    WaitGroup,
+};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+static POINTS: Mutex<Vec<(String, Point)>> = Mutex::new(Vec::new());
+static GATE: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+fn hook(point: Point) {
+    let name = thread::current().name().unwrap_or_default().to_string();
+    let waiter = name == "waiter";
+    POINTS.lock().unwrap().push((name, point));
+    if waiter && point == Point::SetWaiting {
+        let mut open = GATE.0.lock().unwrap();
+        while !*open {
+            open = GATE.1.wait(open).unwrap();
+        }
+    }
+}
+
+/// done() completes between the first load of the waiter and its registration
+#[test]
+fn test_done_before_set_waiting() {
+    hooks::set_hook(Some(hook));
+    let wg = WaitGroup::new();
+    wg.add(1);
+    let _wg = wg.clone();
+    let waiter = thread::Builder::new()
+        .name("waiter".to_string())
+        .spawn(move || futures::executor::block_on(_wg.wait()))
+        .unwrap();
+    while !POINTS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(_, point)| *point == Point::SetWaiting)
+    {
+        thread::yield_now();
+    }
+    wg.done();
+    *GATE.0.lock().unwrap() = true;
+    GATE.1.notify_all();
+    waiter.join().unwrap();
+    hooks::set_hook(None);
+    let points: Vec<Point> = POINTS.lock().unwrap().iter().map(|(_, p)| *p).collect();
+    assert_eq!(
+        points,
+        [
+            Point::LoadLeft,
+            Point::SetWaiting,
+            Point::Decrease,
+            Point::LoadWaiting,
+            Point::Recheck
+        ]
+    );
+}
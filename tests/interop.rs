@@ -0,0 +1,103 @@
+use atomic_waitgroup::WaitGroup;
+use futures_concurrency::future::{Join, Race};
+use std::time::Duration;
+
+#[test]
+fn test_join() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let wg1 = WaitGroup::new();
+        let wg2 = WaitGroup::new();
+        let guard1 = wg1.add_guard();
+        let guard2 = wg2.add_guard();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard1);
+            drop(guard2);
+        });
+        let (r1, r2) = (wg1.wait(), wg2.wait_to(0)).join().await;
+        assert!(r1.blocked && r2.blocked);
+        // Moved into a spawned task
+        let guard = wg1.add_guard();
+        let _wg1 = wg1.clone();
+        let th = tokio::spawn(async move { [_wg1.wait_to(0)].join().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(guard);
+        assert!(th.await.unwrap()[0].blocked);
+    });
+}
+
+#[test]
+fn test_race() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let wg1 = WaitGroup::new();
+        let wg2 = WaitGroup::new();
+        let _guard1 = wg1.add_guard();
+        let guard2 = wg2.add_guard();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard2);
+        });
+        let r = (wg1.wait(), wg2.wait_to(0)).race().await;
+        assert!(r.blocked);
+        assert_eq!(r.left, 0);
+        // The loser is canceled on drop, and can wait again
+        assert_eq!(wg1.is_waiting(), None);
+        assert!(tokio::time::timeout(Duration::from_millis(10), wg1.wait())
+            .await
+            .is_err());
+    });
+}
+
+#[test]
+fn test_race_by_ref() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let wg1 = WaitGroup::new();
+        let wg2 = WaitGroup::new();
+        let _guard1 = wg1.add_guard();
+        let guard2 = wg2.add_guard();
+        // Unpin, raced by reference and kept afterwards
+        let mut w1 = wg1.wait();
+        let mut w2 = wg2.wait();
+        w1.enable();
+        w2.enable();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard2);
+        });
+        assert!((&mut w1, &mut w2).race().await.blocked);
+        #[cfg(feature = "futures-core")]
+        {
+            use futures::future::FusedFuture;
+            assert!(w2.is_terminated());
+            assert!(!w1.is_terminated());
+        }
+        // The loser is still registered until dropped
+        assert_eq!(wg1.is_waiting(), Some(0));
+        drop(w1);
+        assert_eq!(wg1.is_waiting(), None);
+    });
+}
+
+#[cfg(feature = "futures-timer")]
+#[test]
+fn test_futures_timer() {
+    use atomic_waitgroup::{Elapsed, FuturesTimer};
+    let wg = WaitGroup::new();
+    let guard = wg.add_guard();
+    // No tokio runtime involved
+    futures::executor::block_on(async {
+        let r = wg
+            .wait_to_timeout(0, Duration::from_millis(10), &FuturesTimer)
+            .await;
+        assert_eq!(r, Err(Elapsed));
+        let th = std::thread::spawn(move || drop(guard));
+        let r = wg
+            .wait_to_timeout(0, Duration::from_secs(10), &FuturesTimer)
+            .await;
+        assert!(r.is_ok());
+        th.join().unwrap();
+    });
+}
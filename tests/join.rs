@@ -0,0 +1,36 @@
+#![cfg(feature = "tokio")]
+
+use atomic_waitgroup::WaitGroup;
+use std::time::Duration;
+
+#[test]
+fn test_attach_to() {
+    let wg = WaitGroup::new();
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        // Not awaited
+        let handle = tokio::spawn(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        });
+        let _handle = wg.attach_to(handle);
+        assert_eq!(wg.left(), 1);
+        wg.wait().await;
+        // Aborted
+        let handle = tokio::spawn(async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+        let handle = wg.attach_to(handle);
+        handle.abort();
+        wg.wait().await;
+        assert!(handle.await.unwrap_err().is_cancelled());
+        // Detached
+        let handle = tokio::spawn(async { 1 });
+        drop(wg.attach_to(handle));
+        wg.wait().await;
+        assert_eq!(wg.left(), 0);
+    });
+}
@@ -0,0 +1,86 @@
+use atomic_waitgroup::LocalWaitGroup;
+use std::time::Duration;
+use tokio::{task::LocalSet, time::sleep};
+
+fn run_local<F: std::future::Future>(f: F) -> F::Output {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    LocalSet::new().block_on(&rt, f)
+}
+
+#[test]
+fn test_local_wait_to() {
+    let wg = LocalWaitGroup::new();
+    run_local(async move {
+        for _i in 0..10 {
+            let _guard = wg.add_guard();
+            tokio::task::spawn_local(async move {
+                sleep(Duration::from_millis(_i * 10)).await;
+                drop(_guard);
+            });
+        }
+        assert!(wg.wait_to(3).await.blocked);
+        assert!(wg.left() <= 3);
+        wg.wait().await;
+        assert!(!wg.wait_to(0).await.blocked);
+    });
+}
+
+#[test]
+fn test_local_cancel() {
+    let wg = LocalWaitGroup::new();
+    run_local(async move {
+        wg.add(1);
+        assert!(tokio::time::timeout(Duration::from_millis(100), wg.wait())
+            .await
+            .is_err());
+        let _wg = wg.clone();
+        tokio::task::spawn_local(async move {
+            sleep(Duration::from_millis(100)).await;
+            _wg.done();
+        });
+        wg.wait().await;
+        assert_eq!(wg.left(), 0);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_local_done_overflow() {
+    let wg = LocalWaitGroup::new();
+    wg.add(1);
+    wg.done_many(2);
+}
+
+struct TokioTimer;
+
+impl atomic_waitgroup::Timer for TokioTimer {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep_until(&self, deadline: std::time::Instant) -> Self::Sleep {
+        tokio::time::sleep_until(deadline.into())
+    }
+}
+
+#[test]
+fn test_local_track_and_timeout() {
+    let wg = LocalWaitGroup::new();
+    run_local(async move {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        tokio::task::spawn_local(wg.track(async move {
+            let _ = rx.await;
+        }));
+        assert_eq!(wg.left(), 1);
+        let r = wg
+            .wait_to_timeout(0, Duration::from_millis(100), &TokioTimer)
+            .await;
+        assert_eq!(r, Err(atomic_waitgroup::Elapsed));
+        tx.send(()).unwrap();
+        let r = wg
+            .wait_to_timeout(0, Duration::from_secs(10), &TokioTimer)
+            .await;
+        assert!(r.unwrap().blocked);
+    });
+}
@@ -0,0 +1,69 @@
+use atomic_waitgroup::{KeyStats, WaitGroupMap};
+use std::time::Duration;
+
+#[test]
+fn test_wait_group_map() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let map = WaitGroupMap::new();
+        let a1 = map.add_guard("a");
+        let a2 = map.add_guard("a");
+        let b = map.add_guard("b");
+        assert_eq!(a1.key(), &"a");
+        assert_eq!(map.left(&"a"), 2);
+        assert_eq!(map.left_all(), 3);
+        let mut keys = map.keys();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+        drop(a1);
+        assert_eq!(
+            map.stats(&"a"),
+            Some(KeyStats {
+                left: 1,
+                added: 2,
+                completed: 1
+            })
+        );
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(a2);
+        });
+        // Other keys continue
+        map.wait_key(&"a").await;
+        assert_eq!(map.left(&"a"), 0);
+        assert!(map.stats(&"a").is_none());
+        assert_eq!(map.keys(), ["b"]);
+        // Nothing to wait for
+        map.wait_key(&"c").await;
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(b);
+        });
+        map.wait_all_keys().await;
+        assert!(map.keys().is_empty());
+        assert_eq!(map.left_all(), 0);
+    });
+}
+
+#[test]
+fn test_wait_key_to() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let map = WaitGroupMap::new();
+        let db: Vec<_> = (0..3).map(|_| map.add_guard("db")).collect();
+        let _http = map.add_guard("http");
+        assert!(!map.wait_key_to(&"db", 3).await);
+        tokio::spawn(async move {
+            for guard in db {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                drop(guard);
+            }
+        });
+        assert!(map.wait_key_to(&"db", 1).await);
+        assert!(map.left(&"db") <= 1);
+        map.wait_key(&"db").await;
+        // The other label still outstanding
+        assert_eq!(map.left(&"http"), 1);
+        assert!(!map.wait_key_to(&"db", 0).await);
+    });
+}
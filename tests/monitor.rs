@@ -0,0 +1,34 @@
+#![cfg(feature = "monitor")]
+
+use atomic_waitgroup::{monitor, DrainState, WaitGroup};
+use std::time::Duration;
+
+#[test]
+fn test_monitor() {
+    let wg = WaitGroup::new();
+    let registration = wg.register_monitor("ingest");
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || {
+        let _registration = _wg.register_monitor("flush");
+        _wg.wait_blocking()
+    });
+    wg.add(2);
+    std::thread::sleep(Duration::from_millis(50));
+    let rows = monitor::rows();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].name, "ingest");
+    assert_eq!(rows[0].left, 2);
+    assert_eq!(rows[0].waiters, 1);
+    assert_eq!(rows[0].state, DrainState::Open);
+    wg.close();
+    let mut table = Vec::new();
+    monitor::render(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.starts_with("NAME"));
+    assert!(table.contains("flush"));
+    assert!(table.contains("Closed"));
+    wg.done_many(2);
+    th.join().unwrap();
+    drop(registration);
+    assert!(monitor::rows().is_empty());
+}
@@ -0,0 +1,41 @@
+use atomic_waitgroup::{set_default_observer, WaitGroup, WaitGroupObserver};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[derive(Default)]
+struct Counter {
+    adds: AtomicUsize,
+    zeros: AtomicUsize,
+}
+
+impl WaitGroupObserver for Counter {
+    fn on_add(&self, count: usize, _left: usize) {
+        self.adds.fetch_add(count, Ordering::SeqCst);
+    }
+
+    fn on_zero(&self) {
+        self.zeros.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_default_observer() {
+    let before = WaitGroup::new();
+    let default = Arc::new(Counter::default());
+    set_default_observer(Some(default.clone()));
+    let own = Arc::new(Counter::default());
+    let with_own = WaitGroup::builder().observer(own.clone()).build();
+    let wgs = [WaitGroup::new(), WaitGroup::builder().build()];
+    set_default_observer(None);
+    let after = WaitGroup::new();
+    for wg in wgs.iter().chain([&before, &with_own, &after]) {
+        wg.add(2);
+        wg.done_many(2);
+    }
+    assert_eq!(default.adds.load(Ordering::SeqCst), 4);
+    assert_eq!(default.zeros.load(Ordering::SeqCst), 2);
+    assert_eq!(own.adds.load(Ordering::SeqCst), 2);
+    assert_eq!(own.zeros.load(Ordering::SeqCst), 1);
+}
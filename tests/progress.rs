@@ -0,0 +1,23 @@
+#![cfg(feature = "indicatif")]
+
+use atomic_waitgroup::WaitGroup;
+use indicatif::ProgressBar;
+
+#[test]
+fn test_progress_bar() {
+    let wg = WaitGroup::new();
+    wg.add(4);
+    let progress = wg.progress_bar(ProgressBar::hidden());
+    assert_eq!(progress.bar().length(), Some(4));
+    wg.done();
+    assert_eq!(progress.bar().position(), 1);
+    // Added during the drain
+    wg.add(2);
+    wg.done_many(2);
+    assert_eq!(progress.bar().position(), 3);
+    assert_eq!(progress.bar().length(), Some(6));
+    let bar = progress.bar().clone();
+    drop(progress);
+    wg.done_many(3);
+    assert_eq!(bar.position(), 3);
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "diagnostics")]
+
+use atomic_waitgroup::WaitGroup;
+use std::time::Duration;
+
+#[test]
+fn test_overdue() {
+    let wg = WaitGroup::new();
+    let late = wg.add_guard_with_deadline(Duration::from_millis(10));
+    let on_time = wg.add_guard_with_deadline(Duration::from_secs(60));
+    let _untagged = wg.add_guard();
+    assert!(wg.overdue().is_empty());
+    std::thread::sleep(Duration::from_millis(20));
+    let overdue = wg.overdue();
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0].id, late.id());
+    assert_eq!(overdue[0].location.file(), file!());
+    assert_eq!(wg.left(), 3);
+    drop(late);
+    assert!(wg.overdue().is_empty());
+    assert_eq!(wg.left(), 2);
+    drop(on_time);
+    assert_eq!(wg.left(), 1);
+}
+
+#[test]
+fn test_outstanding() {
+    let wg = WaitGroup::new();
+    let a = wg.add_guard_with_info("fetch", 42);
+    let b = wg.add_guard_with_deadline(Duration::from_secs(60));
+    let outstanding = wg.outstanding();
+    assert_eq!(outstanding.len(), 2);
+    assert_eq!(outstanding[0].id, a.id());
+    assert_eq!(outstanding[0].name.as_deref(), Some("fetch"));
+    assert_eq!(outstanding[0].user_data, 42);
+    assert!(outstanding[0].deadline.is_none());
+    assert_eq!(outstanding[1].id, b.id());
+    assert!(outstanding[1].name.is_none());
+    assert!(outstanding[0].added_at <= outstanding[1].added_at);
+    drop(a);
+    let outstanding = wg.outstanding();
+    assert_eq!(outstanding.len(), 1);
+    assert_eq!(outstanding[0].id, b.id());
+}
+
+#[test]
+fn test_oldest_outstanding_age() {
+    let wg = WaitGroup::new();
+    assert!(wg.oldest_outstanding_age().is_none());
+    let old = wg.add_guard_with_info("old", 0);
+    std::thread::sleep(Duration::from_millis(20));
+    let new = wg.add_guard_with_info("new", 0);
+    let age = wg.oldest_outstanding_age().unwrap();
+    assert!(age >= Duration::from_millis(20));
+    drop(old);
+    assert!(wg.oldest_outstanding_age().unwrap() < age);
+    drop(new);
+    assert!(wg.oldest_outstanding_age().is_none());
+}
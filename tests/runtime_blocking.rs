@@ -0,0 +1,50 @@
+#![cfg(feature = "tokio")]
+
+use atomic_waitgroup::{RuntimeBlockingPolicy, WaitGroup};
+use std::time::Duration;
+
+fn make_runtime(threads: usize) -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(threads)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_block_in_place() {
+    let wg = WaitGroup::builder()
+        .runtime_blocking_policy(RuntimeBlockingPolicy::BlockInPlace)
+        .build();
+    make_runtime(1).block_on(async {
+        let guard = wg.add_guard();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard);
+        });
+        // On the only worker, which the blocking wait hands over to the task above
+        let th = tokio::spawn(async move { wg.wait_blocking() });
+        th.await.unwrap();
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_runtime_blocking_panic() {
+    let wg = WaitGroup::builder()
+        .runtime_blocking_policy(RuntimeBlockingPolicy::Panic)
+        .build();
+    let _guard = wg.add_guard();
+    make_runtime(1).block_on(async {
+        let _ = wg.wait_blocking_timeout(Duration::from_millis(1));
+    });
+}
+
+#[test]
+fn test_runtime_blocking_outside() {
+    let wg = WaitGroup::builder()
+        .runtime_blocking_policy(RuntimeBlockingPolicy::Panic)
+        .build();
+    let _guard = wg.add_guard();
+    assert!(wg.wait_blocking_timeout(Duration::from_millis(1)).is_err());
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "statsd")]
+
+use atomic_waitgroup::{StatsdExporter, WaitGroup};
+use std::{net::UdpSocket, time::Duration};
+
+fn recv(socket: &UdpSocket) -> String {
+    let mut buf = [0u8; 1024];
+    let n = socket.recv(&mut buf).unwrap();
+    String::from_utf8_lossy(&buf[..n]).into_owned()
+}
+
+#[test]
+fn test_statsd_on_done() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let wg = WaitGroup::new();
+    wg.add(3);
+    let exporter = StatsdExporter::new(socket.local_addr().unwrap(), "jobs")
+        .unwrap()
+        .with_tag("env", "test")
+        .with_tag("shard", "1");
+    let registration = wg.export_statsd(exporter);
+    assert_eq!(recv(&socket), "jobs.outstanding:3|g|#env:test,shard:1");
+    wg.done_many(2);
+    assert_eq!(
+        recv(&socket),
+        "jobs.outstanding:1|g|#env:test,shard:1\njobs.completed:2|c|#env:test,shard:1"
+    );
+    drop(registration);
+    wg.done();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let mut buf = [0u8; 1024];
+    assert!(socket.recv(&mut buf).is_err());
+}
+
+#[test]
+fn test_statsd_interval() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let wg = WaitGroup::new();
+    wg.add(2);
+    let exporter = StatsdExporter::new(socket.local_addr().unwrap(), "jobs")
+        .unwrap()
+        .with_interval(Duration::from_millis(50));
+    let _registration = wg.export_statsd(exporter);
+    assert_eq!(recv(&socket), "jobs.outstanding:2|g");
+    wg.done();
+    // Sampled, not sent on done()
+    loop {
+        let packet = recv(&socket);
+        if packet != "jobs.outstanding:2|g" {
+            assert_eq!(packet, "jobs.outstanding:1|g\njobs.completed:1|c");
+            break;
+        }
+    }
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "tokio-util")]
+
+use atomic_waitgroup::WaitGroup;
+use std::time::Duration;
+use tokio_util::task::TaskTracker;
+
+#[test]
+fn test_task_tracker() {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let wg = WaitGroup::new();
+        let tracker = TaskTracker::new();
+        // Legacy tasks on the tracker, waited by the WaitGroup
+        let handle = wg.attach_task_tracker(&tracker);
+        for _ in 0..4 {
+            tracker.spawn(tokio::time::sleep(Duration::from_millis(10)));
+        }
+        // Migrated work on the WaitGroup, waited by the tracker
+        let guard = wg.add_guard_with_tracker(&tracker);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(guard);
+        });
+        tracker.close();
+        tracker.wait().await;
+        assert!(tracker.is_empty());
+        wg.wait().await;
+        handle.await.unwrap();
+    });
+}
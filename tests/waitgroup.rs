@@ -4,11 +4,11 @@ use std::time::Duration;
 use tokio::time::sleep;
 
 fn make_runtime(threads: usize) -> tokio::runtime::Runtime {
-    return tokio::runtime::Builder::new_multi_thread()
+    tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .worker_threads(threads)
         .build()
-        .unwrap();
+        .unwrap()
 }
 
 #[test]
@@ -149,3 +149,2240 @@ fn test_done_overflow() {
     wg.add(1);
     wg.done_many(2);
 }
+
+#[test]
+fn test_wait_progress() {
+    let wg = WaitGroup::new();
+    make_runtime(4).block_on(async move {
+        for _i in 0..10 {
+            let _guard = wg.add_guard();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(_i * 50)).await;
+                drop(_guard);
+            });
+        }
+        let mut seen = Vec::new();
+        assert!(wg.wait_to_with_progress(2, |left| seen.push(left)).await);
+        assert!(!seen.is_empty());
+        assert!(*seen.last().unwrap() <= 2);
+        for w in seen.windows(2) {
+            assert!(w[0] > w[1]);
+        }
+        assert!(
+            !wg.wait_to_with_progress(2, |_| panic!("no progress expected"))
+                .await
+        );
+        wg.wait().await;
+    });
+}
+
+#[test]
+fn test_watchdog() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    let wg = WaitGroup::new();
+    let stalls = Arc::new(AtomicUsize::new(0));
+    let _stalls = stalls.clone();
+    let watchdog = wg.watchdog_with(Duration::from_millis(100), move |wg| {
+        assert_eq!(wg.left(), 1);
+        _stalls.fetch_add(1, Ordering::SeqCst);
+    });
+    let _guard = wg.add_guard();
+    std::thread::sleep(Duration::from_millis(500));
+    // Reported once per stall
+    assert_eq!(stalls.load(Ordering::SeqCst), 1);
+    drop(_guard);
+    assert_eq!(wg.completed(), 1);
+    drop(watchdog);
+}
+
+#[test]
+fn test_drop_policy() {
+    use atomic_waitgroup::DropPolicy;
+    let wg = WaitGroup::builder()
+        .drop_policy(DropPolicy::DebugPanic)
+        .build();
+    let guard = wg.add_guard();
+    let _wg = wg.clone();
+    drop(wg);
+    drop(guard);
+    // Not the last handle above, and nothing left now
+    drop(_wg);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn test_drop_policy_panic() {
+    use atomic_waitgroup::DropPolicy;
+    let wg = WaitGroup::builder()
+        .drop_policy(DropPolicy::DebugPanic)
+        .build();
+    wg.add(1);
+    drop(wg);
+}
+
+#[test]
+fn test_poison() {
+    use atomic_waitgroup::Poisoned;
+    let wg = WaitGroup::builder().poison_on_panic(true).build();
+    make_runtime(2).block_on(async move {
+        let _guard = wg.add_guard();
+        let th = std::thread::spawn(move || {
+            let _guard = _guard;
+            panic!("worker failed");
+        });
+        assert!(th.join().is_err());
+        assert!(wg.is_poisoned());
+        assert_eq!(wg.wait_result().await, Err(Poisoned { count: 1 }));
+        wg.clear_poison();
+        let _guard = wg.add_guard();
+        drop(_guard);
+        assert_eq!(wg.wait_result().await, Ok(()));
+    });
+}
+
+#[test]
+fn test_wrap_fn() {
+    let wg = WaitGroup::new();
+    let token = wg.wrap_fn();
+    let f = wg.wrap_fn().into_fn();
+    let _token = wg.wrap_fn();
+    assert_eq!(wg.left(), 3);
+    token.call();
+    assert_eq!(wg.left(), 2);
+    std::thread::spawn(f).join().unwrap();
+    assert_eq!(wg.left(), 1);
+    drop(_token);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_done_waker() {
+    let wg = WaitGroup::new();
+    let waker = wg.done_waker();
+    let _waker = waker.clone();
+    assert_eq!(wg.left(), 1);
+    drop(waker);
+    assert_eq!(wg.left(), 1);
+    // The last clone dropped without wake
+    drop(_waker);
+    assert_eq!(wg.left(), 0);
+    make_runtime(1).block_on(async move {
+        let waker = wg.done_waker();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            waker.wake_by_ref();
+        });
+        wg.wait().await;
+    });
+}
+
+#[cfg(all(unix, feature = "eventfd"))]
+#[test]
+fn test_eventfd() {
+    use std::os::fd::{AsFd, FromRawFd, OwnedFd};
+    let fd = unsafe { OwnedFd::from_raw_fd(libc::eventfd(0, libc::EFD_NONBLOCK)) };
+    let read = |fd: &OwnedFd| -> u64 {
+        use std::os::fd::AsRawFd;
+        let mut val: u64 = 0;
+        let r = unsafe { libc::read(fd.as_raw_fd(), &mut val as *mut u64 as *mut _, 8) };
+        if r < 0 {
+            0
+        } else {
+            val
+        }
+    };
+    let wg = WaitGroup::new();
+    wg.add(3);
+    let reg = wg.register_eventfd(fd.as_fd(), 1);
+    assert_eq!(read(&fd), 0);
+    wg.done();
+    assert_eq!(read(&fd), 0);
+    wg.done();
+    assert_eq!(read(&fd), 1);
+    wg.done();
+    assert_eq!(read(&fd), 0);
+    drop(reg);
+    wg.add(2);
+    wg.done_many(2);
+    assert_eq!(read(&fd), 0);
+}
+
+#[test]
+fn test_multi_waiters() {
+    use atomic_waitgroup::MultiWaitGroup;
+    let wg = MultiWaitGroup::<3>::new();
+    make_runtime(4).block_on(async move {
+        for _i in 0..10 {
+            let _guard = wg.add_guard();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(_i * 20)).await;
+                drop(_guard);
+            });
+        }
+        let mut ths = Vec::new();
+        for target in [5, 2] {
+            let _wg = wg.clone();
+            ths.push(tokio::spawn(async move {
+                assert!(_wg.wait_to(target).await.blocked);
+                assert!(_wg.left() <= target);
+            }));
+        }
+        wg.wait().await;
+        for th in ths {
+            th.await.unwrap();
+        }
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_multi_waiters_full() {
+    use atomic_waitgroup::MultiWaitGroup;
+    let wg = MultiWaitGroup::<1>::new();
+    make_runtime(1).block_on(async move {
+        wg.add(1);
+        let _wg = wg.clone();
+        tokio::spawn(async move {
+            _wg.wait().await;
+        });
+        sleep(Duration::from_millis(100)).await;
+        wg.wait().await;
+    });
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_assert_settles_within() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        for i in 0..5 {
+            let _guard = wg.add_guard();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(i * 10)).await;
+                drop(_guard);
+            });
+        }
+        wg.assert_settles_within(Duration::from_secs(1)).await;
+    });
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+#[should_panic(expected = "not settled within")]
+fn test_assert_settles_within_stalled() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        let _guard = wg.add_guard();
+        wg.assert_settles_within(Duration::from_millis(50)).await;
+    });
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_observation() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        assert_eq!(wg.waker_id(), 0);
+        assert_eq!(wg.waiting_target(), None);
+        assert!(!wg.has_waker());
+        wg.add(2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            assert!(_wg.wait_to(1).await.blocked);
+        });
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(wg.waker_id(), 1);
+        assert_eq!(wg.waiting_target(), Some(1));
+        assert!(wg.has_waker());
+        wg.done();
+        th.await.unwrap();
+        assert_eq!(wg.waker_id(), 1);
+        assert_eq!(wg.waiting_target(), None);
+        assert!(!wg.has_waker());
+    });
+}
+
+#[test]
+fn test_done_underflow() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::sync::{Arc, Mutex};
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    wg.add(1);
+    wg.done_many(2);
+    assert_eq!(wg.left(), 1);
+    assert_eq!(
+        *errors.lock().unwrap(),
+        [CountError::Underflow { left: 1, count: 2 }]
+    );
+    wg.done();
+    assert_eq!(wg.left(), 0);
+    // Panics with the default policy, the count is not changed
+    let wg = WaitGroup::new();
+    let _wg = wg.clone();
+    assert!(std::thread::spawn(move || _wg.done()).join().is_err());
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_transfer() {
+    let stage1 = WaitGroup::new();
+    let stage2 = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        stage1.add(10);
+        let (_stage1, _stage2) = (stage1.clone(), stage2.clone());
+        let th = tokio::spawn(async move {
+            for _ in 0..10 {
+                sleep(Duration::from_millis(5)).await;
+                _stage1.transfer(1, &_stage2);
+                // The item is always counted by one of the stages
+                assert!(_stage1.left() + _stage2.left() >= 1);
+                _stage2.done();
+            }
+        });
+        stage1.wait().await;
+        assert_eq!(stage1.left(), 0);
+        stage2.wait().await;
+        th.await.unwrap();
+        assert_eq!(stage1.completed(), 10);
+        assert_eq!(stage2.completed(), 10);
+    });
+}
+
+#[test]
+fn test_transfer_underflow() {
+    use atomic_waitgroup::ErrorPolicy;
+    let stage1 = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    let stage2 = WaitGroup::new();
+    stage1.add(1);
+    stage1.transfer(2, &stage2);
+    assert_eq!(stage1.left(), 1);
+    assert_eq!(stage2.left(), 0);
+    // Also undone when the underflow panics
+    let stage1 = WaitGroup::new();
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| stage1.transfer(1, &stage2)));
+    assert!(r.is_err());
+    assert_eq!(stage2.left(), 0);
+    // Nothing moved into a closed group
+    let stage1 = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    let closed = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    closed.close();
+    stage1.add(1);
+    stage1.transfer(1, &closed);
+    assert_eq!(stage1.left(), 1);
+    assert_eq!(closed.left(), 0);
+    // The part keeps the options
+    let part = stage1.split_off(1);
+    assert_eq!(stage1.left(), 0);
+    part.done_many(2);
+    assert_eq!(part.left(), 1);
+    part.done();
+}
+
+#[test]
+fn test_guard_handoff() {
+    let stage1 = WaitGroup::new();
+    let stage2 = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        let guards: Vec<_> = (0..10).map(|_| stage1.add_guard()).collect();
+        let (_stage1, _stage2) = (stage1.clone(), stage2.clone());
+        let th = tokio::spawn(async move {
+            for guard in guards {
+                sleep(Duration::from_millis(5)).await;
+                let guard = guard.handoff(&_stage2).ok().unwrap();
+                assert!(_stage1.left() + _stage2.left() >= 1);
+                drop(guard);
+            }
+        });
+        stage1.wait().await;
+        stage2.wait().await;
+        th.await.unwrap();
+        assert_eq!(stage2.completed(), 10);
+        let mut guard = stage1.add_guard();
+        guard.release();
+        let guard = guard.handoff(&stage2).ok().unwrap();
+        assert!(!guard.is_armed());
+        assert_eq!((stage1.left(), stage2.left()), (0, 0));
+    });
+}
+
+#[test]
+fn test_guard_handoff_closed() {
+    use atomic_waitgroup::ErrorPolicy;
+    let stage1 = WaitGroup::new();
+    let stage2 = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    stage2.close();
+    let guard = stage1.add_guard();
+    // Rejected, the source guard is kept
+    let guard = guard.handoff(&stage2).err().unwrap();
+    assert!(guard.is_armed());
+    assert_eq!((stage1.left(), stage2.left()), (1, 0));
+    drop(guard);
+    assert_eq!(stage1.left(), 0);
+}
+
+#[test]
+fn test_merge_from() {
+    let global = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        global.add(1);
+        for shard in 0..3 {
+            let wg = WaitGroup::new();
+            for i in 0..3 {
+                let _guard = wg.add_guard();
+                tokio::spawn(async move {
+                    sleep(Duration::from_millis(10 * (shard + i))).await;
+                    drop(_guard);
+                });
+            }
+            global.merge_from(&wg);
+        }
+        assert!(global.left() <= 10);
+        global.done();
+        global.wait().await;
+        assert_eq!(global.left(), 0);
+    });
+    let wg = WaitGroup::new();
+    let other = WaitGroup::new();
+    other.add(2);
+    wg.merge_from(&other);
+    assert_eq!(wg.left(), 2);
+    // Added after the merge, not forwarded
+    other.add(1);
+    other.done();
+    assert_eq!(wg.left(), 2);
+    other.done_many(2);
+    assert_eq!(wg.left(), 0);
+    assert_eq!(other.left(), 0);
+    other.add(1);
+    other.done();
+    assert_eq!(wg.left(), 0);
+    // Forwarded from interrupt handlers once flushed
+    other.add(1);
+    wg.merge_from(&other);
+    other.done_from_isr();
+    assert_eq!(other.left(), 0);
+    assert_eq!(wg.left(), 1);
+    other.flush_isr();
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_done_from_isr_blocking() {
+    let wg = WaitGroup::new();
+    wg.add(2);
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || _wg.wait_blocking());
+    std::thread::sleep(Duration::from_millis(50));
+    wg.done_from_isr();
+    wg.done_from_isr();
+    assert_eq!(wg.left(), 0);
+    wg.flush_isr();
+    th.join().unwrap();
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_split_off() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        wg.add(5);
+        let part = wg.split_off(2);
+        assert_eq!(wg.left(), 3);
+        assert_eq!(part.left(), 2);
+        let _part = part.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            _part.done_many(2);
+        });
+        part.wait().await;
+        assert_eq!(wg.left(), 3);
+        wg.done_many(3);
+        wg.wait().await;
+    });
+}
+
+#[test]
+fn test_snapshot_delta() {
+    let wg = WaitGroup::new();
+    wg.add(5);
+    wg.done_many(2);
+    let first = wg.snapshot();
+    assert_eq!((first.added, first.completed), (5, 2));
+    std::thread::sleep(Duration::from_millis(10));
+    wg.add(1);
+    wg.done_many(4);
+    let delta = wg.delta_since(&first);
+    assert_eq!((delta.added, delta.completed), (1, 4));
+    assert!(delta.elapsed >= Duration::from_millis(10));
+    let second = wg.snapshot();
+    assert_eq!(second.delta_since(&first).completed, 4);
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn test_stats() {
+    let wg = WaitGroup::new();
+    assert_eq!(wg.stats().outstanding_avg, 0.0);
+    for _ in 0..100 {
+        wg.add(10);
+        wg.done_many(10);
+        wg.add(10);
+    }
+    let stats = wg.stats();
+    assert_eq!(stats.left, 1000);
+    assert_eq!(stats.added, 2000);
+    assert_eq!(stats.completed, 1000);
+    assert!(stats.outstanding_avg > 900.0 && stats.outstanding_avg < 1000.0);
+    wg.done_many(1000);
+    let avg = wg.stats().outstanding_avg;
+    assert!(avg < stats.outstanding_avg);
+    for _ in 0..100 {
+        wg.add(1);
+        wg.done();
+    }
+    assert!(wg.stats().outstanding_avg < 1.0);
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn test_stats_waits() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async {
+        // Not blocked, not counted
+        wg.wait().await;
+        wg.add(1);
+        let r = tokio::time::timeout(Duration::from_millis(10), wg.wait()).await;
+        assert!(r.is_err());
+        let deadline = std::time::Instant::now() + Duration::from_millis(10);
+        assert!(wg.wait_to_deadline(0, deadline).await.is_err());
+        let _wg = wg.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            _wg.done();
+        });
+        wg.wait().await;
+    });
+    let stats = wg.stats();
+    assert_eq!(stats.waits_completed, 1);
+    assert_eq!(stats.waits_canceled, 2);
+}
+
+#[test]
+fn test_adaptive_limiter() {
+    use atomic_waitgroup::AdaptiveLimiter;
+    let wg = WaitGroup::new();
+    let limiter = AdaptiveLimiter::new(wg.clone(), 1, 8).with_window(Duration::from_millis(10));
+    make_runtime(2).block_on(async move {
+        // Completions keep up, the limit increases
+        for _ in 0..200 {
+            let _guard = limiter.acquire().await;
+            assert!(wg.left() <= 8);
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(1)).await;
+                drop(_guard);
+            });
+        }
+        wg.wait().await;
+        let limit = limiter.limit();
+        assert!(limit > 1);
+        // Arrivals without completion, the limit decreases
+        let mut guards = Vec::new();
+        while let Some(guard) = limiter.try_acquire() {
+            guards.push(guard);
+        }
+        sleep(Duration::from_millis(20)).await;
+        for _ in 0..=limit {
+            assert!(limiter.try_acquire().is_none());
+        }
+        sleep(Duration::from_millis(20)).await;
+        assert!(limiter.try_acquire().is_none());
+        assert!(limiter.limit() < limit);
+        // Waiters are woken on done()
+        let th = {
+            let wg = wg.clone();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(50)).await;
+                drop(guards);
+                wg.left()
+            })
+        };
+        let _guard = limiter.acquire().await;
+        assert!(th.await.unwrap() <= 1);
+    });
+}
+
+#[test]
+fn test_wait_blocking() {
+    let wg = WaitGroup::new();
+    wg.add(3);
+    let mut ths = Vec::new();
+    for target in [0, 0, 1, 2, 0] {
+        let _wg = wg.clone();
+        ths.push(std::thread::spawn(move || {
+            assert!(_wg.wait_to_blocking(target));
+            assert!(_wg.left() <= target);
+        }));
+    }
+    for _ in 0..3 {
+        std::thread::sleep(Duration::from_millis(20));
+        wg.done();
+    }
+    for th in ths {
+        th.join().unwrap();
+    }
+    assert!(!wg.wait_to_blocking(0));
+    wg.add(1);
+    assert_eq!(
+        wg.wait_to_blocking_timeout(0, Duration::from_millis(20)),
+        Err(atomic_waitgroup::Elapsed)
+    );
+    wg.done();
+    assert_eq!(
+        wg.wait_to_blocking_timeout(0, Duration::from_millis(20)),
+        Ok(false)
+    );
+    wg.add(1);
+    assert_eq!(
+        wg.wait_blocking_timeout(Duration::from_millis(20)),
+        Err(atomic_waitgroup::Elapsed)
+    );
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || _wg.done());
+    assert_eq!(wg.wait_blocking_timeout(Duration::from_secs(10)), Ok(()));
+    th.join().unwrap();
+}
+
+#[test]
+fn test_wait_callback_thread() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    let wg = WaitGroup::new();
+    wg.add(2);
+    let called = Arc::new(AtomicBool::new(false));
+    let _called = called.clone();
+    let th = wg.wait_callback_thread(1, move || _called.store(true, Ordering::SeqCst));
+    assert_eq!(th.thread().name(), Some("waitgroup-callback"));
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(!called.load(Ordering::SeqCst));
+    wg.done();
+    th.join().unwrap();
+    assert!(called.load(Ordering::SeqCst));
+    assert_eq!(wg.left(), 1);
+}
+
+#[test]
+fn test_blocking_scope() {
+    use atomic_waitgroup::BlockingScope;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    let count = Arc::new(AtomicUsize::new(0));
+    let run = |fail: bool| -> Result<(), ()> {
+        let scope = BlockingScope::new();
+        for i in 0..4 {
+            let guard = scope.guard();
+            let count = count.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10 * i));
+                count.fetch_add(1, Ordering::SeqCst);
+                drop(guard);
+            });
+        }
+        if fail {
+            return Err(());
+        }
+        Ok(())
+    };
+    assert!(run(true).is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+    let _count = count.clone();
+    let r = std::thread::spawn(move || {
+        let scope = BlockingScope::new();
+        let count = _count.clone();
+        scope.spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            count.fetch_add(1, Ordering::SeqCst);
+        });
+        panic!("early");
+    })
+    .join();
+    assert!(r.is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn test_track_caller() {
+    let wg = WaitGroup::new();
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| wg.done()));
+    let msg = r.unwrap_err().downcast::<String>().unwrap();
+    assert!(msg.contains(file!()), "{}", msg);
+    let wg = WaitGroup::new();
+    wg.add(1);
+    make_runtime(1).block_on(async move {
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait().await });
+        sleep(Duration::from_millis(50)).await;
+        let line = line!() + 1;
+        let r = tokio::spawn(async move { wg.wait().await }).await;
+        let msg = r.unwrap_err().into_panic().downcast::<String>().unwrap();
+        assert!(msg.contains(&format!("{}:{}", file!(), line)), "{}", msg);
+        th.abort();
+    });
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn test_wait_timed() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        assert_eq!(wg.wait_timed().await, Duration::ZERO);
+        wg.add(2);
+        let _wg = wg.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            _wg.done();
+            sleep(Duration::from_millis(50)).await;
+            _wg.done();
+        });
+        let blocked = wg.wait_to_timed(1).await;
+        assert!(blocked >= Duration::from_millis(40) && blocked < Duration::from_millis(90));
+        assert!(wg.wait_timed().await >= Duration::from_millis(40));
+    });
+}
+
+#[test]
+fn test_observer() {
+    use atomic_waitgroup::WaitGroupObserver;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl WaitGroupObserver for Recorder {
+        fn on_add(&self, count: usize, left: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("add {} {}", count, left));
+        }
+
+        fn on_done(&self, count: usize, left: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("done {} {}", count, left));
+        }
+
+        fn on_wait_start(&self, target: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("wait_start {}", target));
+        }
+
+        fn on_wait_end(&self, left: usize) {
+            self.0.lock().unwrap().push(format!("wait_end {}", left));
+        }
+
+        fn on_zero(&self) {
+            self.0.lock().unwrap().push("zero".to_string());
+        }
+    }
+
+    let recorder = Arc::new(Recorder::default());
+    let wg = WaitGroup::builder().observer(recorder.clone()).build();
+    make_runtime(1).block_on(async move {
+        wg.add(2);
+        let _wg = wg.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            _wg.done_many(2);
+        });
+        wg.wait().await;
+    });
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        ["add 2 2", "wait_start 0", "done 2 0", "zero", "wait_end 0"]
+    );
+}
+
+#[test]
+fn test_into_raw() {
+    use atomic_waitgroup::{DropPolicy, WaitGroupGuard};
+    let wg = WaitGroup::builder()
+        .drop_policy(DropPolicy::DebugPanic)
+        .build();
+    let guard = wg.add_guard();
+    let raw_guard = guard.into_raw();
+    let raw_wg = wg.clone().into_raw();
+    // The raw handle is still alive
+    drop(wg);
+    let wg = unsafe { WaitGroup::from_raw(raw_wg) };
+    assert_eq!(wg.left(), 1);
+    drop(unsafe { WaitGroupGuard::from_raw(raw_guard) });
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_add_overflow() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::sync::{Arc, Mutex};
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .max_count(10)
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    wg.add(10);
+    assert!(errors.lock().unwrap().is_empty());
+    // Above the ceiling still counted
+    wg.add(2);
+    assert_eq!(wg.left(), 12);
+    // Out of range dropped
+    wg.add(usize::MAX);
+    assert_eq!(wg.left(), 12);
+    // The full range of usize
+    wg.add(usize::MAX - 12);
+    assert_eq!(wg.left(), usize::MAX);
+    assert_eq!(
+        *errors.lock().unwrap(),
+        [
+            CountError::Overflow {
+                left: 10,
+                count: 2,
+                max: 10
+            },
+            CountError::Overflow {
+                left: 12,
+                count: usize::MAX,
+                max: usize::MAX
+            },
+            CountError::Overflow {
+                left: 12,
+                count: usize::MAX - 12,
+                max: 10
+            },
+        ]
+    );
+    wg.done_many(usize::MAX);
+    assert_eq!(wg.left(), 0);
+
+    let wg = WaitGroup::new();
+    wg.add(1);
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| wg.add(usize::MAX)));
+    assert!(r.is_err());
+    assert_eq!(wg.left(), 1);
+    wg.done();
+}
+
+#[test]
+fn test_guard_set() {
+    let wg = WaitGroup::new();
+    let set = wg.guard_set(3);
+    assert_eq!(wg.left(), 3);
+    let t1 = set.ticket().unwrap();
+    let t2 = set.ticket().unwrap();
+    assert_eq!(set.remaining(), 1);
+    // Unused unit returned
+    drop(set);
+    assert_eq!(wg.left(), 2);
+    drop(t1);
+    assert_eq!(wg.left(), 1);
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || _wg.wait_blocking());
+    drop(t2);
+    th.join().unwrap();
+    assert_eq!(wg.left(), 0);
+
+    let set = wg.guard_set(1);
+    let _t = set.ticket().unwrap();
+    assert!(set.ticket().is_none());
+}
+
+#[test]
+fn test_rejected_guard_log_policy() {
+    use atomic_waitgroup::ErrorPolicy;
+    let wg = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    let mut guard = wg.add_guard();
+    wg.close();
+    // Rejected after closed, the count stays with the real guard
+    let rejected = wg.add_guard();
+    assert!(!rejected.is_armed());
+    assert_eq!(wg.left(), 1);
+    drop(rejected);
+    assert_eq!(wg.left(), 1);
+    {
+        let borrowed = wg.add_guard_ref();
+        assert!(!borrowed.is_armed());
+    }
+    assert_eq!(wg.left(), 1);
+    {
+        let set = wg.guard_set(2);
+        assert_eq!(set.remaining(), 0);
+        assert!(set.ticket().is_none());
+    }
+    assert_eq!(wg.left(), 1);
+    drop(wg.wrap_fn());
+    drop(wg.done_waker());
+    assert_eq!(wg.left(), 1);
+    guard.release();
+    assert_eq!(wg.left(), 0);
+    guard.rearm();
+    assert!(!guard.is_armed());
+    drop(guard);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_wait_with_progress_timeout() {
+    use atomic_waitgroup::Elapsed;
+    use std::time::Instant;
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let wg = WaitGroup::new();
+        wg.add(6);
+        let _wg = wg.clone();
+        let start = Instant::now();
+        tokio::spawn(async move {
+            // Longer than idle in total, but never idle for that long
+            for _ in 0..6 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                _wg.done();
+            }
+        });
+        let idle = Duration::from_millis(60);
+        assert_eq!(wg.wait_with_progress_timeout(idle).await, Ok(true));
+        assert!(start.elapsed() > idle);
+
+        wg.add(1);
+        let start = Instant::now();
+        assert_eq!(wg.wait_with_progress_timeout(idle).await, Err(Elapsed));
+        assert!(start.elapsed() >= idle);
+        wg.done();
+        assert_eq!(wg.wait_with_progress_timeout(idle).await, Ok(false));
+    });
+}
+
+#[test]
+fn test_freeze() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let wg = WaitGroup::new();
+        wg.add(1);
+        wg.freeze();
+        assert!(wg.is_frozen());
+        let done = Arc::new(AtomicBool::new(false));
+        let _wg = wg.clone();
+        let _done = done.clone();
+        let th = tokio::spawn(async move {
+            assert!(_wg.wait_to(0).await.blocked);
+            _done.store(true, Ordering::SeqCst);
+        });
+        let _wg = wg.clone();
+        let blocking = std::thread::spawn(move || _wg.wait_to_blocking(0));
+        sleep(Duration::from_millis(10)).await;
+        wg.done();
+        // The condition is true, but held
+        sleep(Duration::from_millis(20)).await;
+        assert!(!done.load(Ordering::SeqCst));
+        assert!(!blocking.is_finished());
+        wg.unfreeze();
+        th.await.unwrap();
+        assert!(blocking.join().unwrap());
+        assert!(!wg.is_frozen());
+    });
+}
+
+#[test]
+fn test_claim_at() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+    let rejected = Arc::new(AtomicUsize::new(0));
+    let _rejected = rejected.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            assert!(matches!(e, CountError::Closed { count: 1 }));
+            _rejected.fetch_add(1, Ordering::SeqCst);
+        })))
+        .build();
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut producers = Vec::new();
+    for _ in 0..4 {
+        let _wg = wg.clone();
+        let _stop = stop.clone();
+        producers.push(std::thread::spawn(move || {
+            while !_stop.load(Ordering::SeqCst) {
+                if _wg.try_add_guard().is_none() {
+                    break;
+                }
+            }
+        }));
+    }
+    make_runtime(2).block_on(async {
+        sleep(Duration::from_millis(10)).await;
+        let left = wg.claim_at(2).await;
+        assert!(left <= 2);
+        assert!(wg.is_closed());
+        // Only goes down from the claimed level
+        for _ in 0..1000 {
+            assert!(wg.left() <= left);
+        }
+    });
+    stop.store(true, Ordering::SeqCst);
+    for th in producers {
+        th.join().unwrap();
+    }
+    assert_eq!(wg.left(), 0);
+    wg.add(1);
+    assert_eq!(wg.left(), 0);
+    assert_eq!(rejected.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_wait_to_and_add() {
+    let wg = WaitGroup::new();
+    make_runtime(4).block_on(async {
+        for i in 0..100 {
+            let chunk = i % 7 + 1;
+            let guard = wg.wait_to_and_add(8, chunk).await.unwrap();
+            assert_eq!(guard.weight(), chunk);
+            assert!(wg.left() <= 8 + chunk);
+            tokio::spawn(async move {
+                sleep(Duration::from_millis((OsRng.next_u32() % 3) as u64)).await;
+                drop(guard);
+            });
+        }
+        wg.wait().await;
+        // Rejected once closed, without a guard
+        wg.close();
+        assert!(wg.wait_to_and_add(8, 1).await.is_none());
+    });
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_try_acquire() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let wg = WaitGroup::new();
+    assert!(wg.try_acquire(0).is_none());
+    let admitted = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    if let Some(_guard) = wg.try_acquire(3) {
+                        assert!(wg.left() <= 3);
+                        admitted.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    assert!(admitted.load(Ordering::Relaxed) > 0);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_add_many_capped() {
+    let wg = WaitGroup::new();
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let added = wg.add_many_capped(4, 10);
+                    assert!(wg.left() <= 10);
+                    wg.done_many(added);
+                }
+            });
+        }
+    });
+    assert_eq!(wg.left(), 0);
+    assert_eq!(wg.add_many_capped(4, 0), 0);
+    wg.add(1);
+    wg.close();
+    assert_eq!(wg.add_many_capped(4, 10), 0);
+    wg.done();
+}
+
+#[test]
+fn test_acquire_fifo() {
+    use std::sync::{Arc, Mutex};
+    let wg = WaitGroup::new();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    make_runtime(4).block_on(async {
+        let first = wg.acquire(1).await.unwrap();
+        let mut tasks = Vec::new();
+        for i in 0..8 {
+            let _wg = wg.clone();
+            let _order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _guard = _wg.acquire(1).await.unwrap();
+                assert_eq!(_wg.left(), 1);
+                _order.lock().unwrap().push(i);
+                sleep(Duration::from_millis(1)).await;
+            }));
+            // Queued in order
+            sleep(Duration::from_millis(5)).await;
+        }
+        // Canceled while queued, the others are not blocked by it
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), wg.acquire(1))
+                .await
+                .is_err()
+        );
+        drop(first);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), (0..8).collect::<Vec<_>>());
+        assert_eq!(wg.left(), 0);
+        // Closed
+        let guard = wg.acquire(1).await.unwrap();
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.acquire(1).await.is_none() });
+        sleep(Duration::from_millis(5)).await;
+        assert_eq!(wg.claim_at(1).await, 1);
+        assert!(th.await.unwrap());
+        drop(guard);
+    });
+}
+
+#[test]
+fn test_acquire_close_race() {
+    use std::future::Future;
+    use std::task::{Context, Waker};
+
+    let mut ctx = Context::from_waker(Waker::noop());
+    for _ in 0..20000 {
+        let wg = WaitGroup::new();
+        let guard = wg.add_guard();
+        let mut acquire = Box::pin(wg.acquire(1));
+        assert!(acquire.as_mut().poll(&mut ctx).is_pending());
+        // The grant by done() losing to close() does not grant again under the queue lock
+        std::thread::scope(|s| {
+            s.spawn(|| drop(guard));
+            s.spawn(|| wg.close());
+        });
+        match acquire.as_mut().poll(&mut ctx) {
+            std::task::Poll::Ready(Some(guard)) => drop(guard),
+            std::task::Poll::Ready(None) => {}
+            std::task::Poll::Pending => panic!("neither granted nor rejected"),
+        }
+        assert_eq!(wg.left(), 0);
+    }
+}
+
+#[test]
+fn test_acquire_weighted() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    let wg = WaitGroup::new();
+    let max = Arc::new(AtomicUsize::new(0));
+    make_runtime(4).block_on(async {
+        let mut tasks = Vec::new();
+        for i in 0..50 {
+            let _wg = wg.clone();
+            let _max = max.clone();
+            tasks.push(tokio::spawn(async move {
+                let weight = i % 5 + 1;
+                let guard = _wg.acquire_weighted(weight, 8).await.unwrap();
+                assert_eq!(guard.weight(), weight);
+                _max.fetch_max(_wg.left(), Ordering::SeqCst);
+                sleep(Duration::from_millis(1)).await;
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // Above the limit, admitted alone
+        let guard = wg.acquire_weighted(10, 8).await.unwrap();
+        assert_eq!(wg.left(), 10);
+        drop(guard);
+    });
+    assert!(max.load(Ordering::SeqCst) <= 8);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_drain_state() {
+    use atomic_waitgroup::{DrainState, ErrorPolicy};
+    let wg = WaitGroup::builder().error_policy(ErrorPolicy::Log).build();
+    let mut watch = wg.watch_state();
+    assert_eq!(watch.last(), DrainState::Open);
+    make_runtime(2).block_on(async {
+        let guard = wg.add_guard();
+        let th = tokio::spawn(async move {
+            let mut phases = Vec::new();
+            while let Some(state) = watch.changed().await {
+                phases.push(state);
+            }
+            phases
+        });
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(wg.state(), DrainState::Open);
+        wg.close();
+        assert_eq!(wg.state(), DrainState::Closed);
+        wg.add(1);
+        assert_eq!(wg.left(), 1);
+        sleep(Duration::from_millis(10)).await;
+        drop(guard);
+        assert_eq!(wg.state(), DrainState::Drained);
+        assert_eq!(th.await.unwrap(), [DrainState::Closed, DrainState::Drained]);
+        // Closed at zero
+        let wg = WaitGroup::new();
+        let mut watch = wg.watch_state();
+        wg.close();
+        assert_eq!(watch.changed().await, Some(DrainState::Drained));
+        assert_eq!(watch.changed().await, None);
+    });
+}
+
+#[test]
+fn test_closed() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        let mut workers = Vec::new();
+        for _ in 0..4 {
+            let guard = wg.add_guard();
+            let closed = wg.closed();
+            workers.push(tokio::spawn(async move {
+                closed.await;
+                drop(guard);
+            }));
+        }
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(wg.left(), 4);
+        wg.close();
+        wg.wait().await;
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        // Already closed
+        wg.closed().await;
+    });
+}
+
+#[test]
+fn test_shutdown_plan() {
+    use std::sync::{Arc, Mutex};
+    let wg = WaitGroup::new();
+    let stages = Arc::new(Mutex::new(Vec::new()));
+    make_runtime(2).block_on(async {
+        let guard = wg.add_guard();
+        let (_s1, _s2, _s3) = (stages.clone(), stages.clone(), stages.clone());
+        let r = wg
+            .shutdown_plan()
+            .stage(Duration::from_millis(20), move |_| {
+                _s2.lock().unwrap().push(2)
+            })
+            .stage(Duration::from_millis(10), move |wg| {
+                assert_eq!(wg.left(), 1);
+                _s1.lock().unwrap().push(1)
+            })
+            .stage(Duration::from_secs(10), move |_| {
+                _s3.lock().unwrap().push(3)
+            })
+            .fail_at(Duration::from_millis(30))
+            .run()
+            .await;
+        assert!(r.is_err());
+        assert_eq!(*stages.lock().unwrap(), [1, 2]);
+        // Drained within the first stage
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(5)).await;
+            drop(guard);
+        });
+        let r = wg
+            .shutdown_plan()
+            .stage(Duration::from_millis(100), |_| panic!("not drained"))
+            .run()
+            .await;
+        assert!(r.is_ok());
+    });
+}
+
+#[test]
+fn test_guard_rearm() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async {
+        let mut guard = wg.add_guard();
+        guard.release();
+        guard.release();
+        assert!(!guard.is_armed());
+        assert_eq!(wg.left(), 0);
+        guard.rearm();
+        guard.rearm();
+        assert_eq!(wg.left(), 1);
+        let th = tokio::spawn(async move {
+            for _ in 0..10 {
+                guard.release();
+                sleep(Duration::from_millis(1)).await;
+                guard.rearm();
+            }
+            guard
+        });
+        // Woken on the first release
+        wg.wait().await;
+        let guard = th.await.unwrap();
+        assert!(guard.is_armed());
+        drop(guard);
+        assert_eq!(wg.left(), 0);
+    });
+}
+
+// Chaos duplicates and delays the wake
+#[cfg(not(feature = "chaos"))]
+#[test]
+fn test_waker_panic() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Wake, Waker};
+
+    struct PanicWaker;
+
+    impl Wake for PanicWaker {
+        fn wake(self: Arc<Self>) {
+            panic!("waker");
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    let waker = Waker::from(Arc::new(PanicWaker));
+    let mut ctx = Context::from_waker(&waker);
+    wg.add(1);
+    let mut f = Box::pin(wg.wait());
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    // Not unwinding into done()
+    wg.done();
+    assert_eq!(*errors.lock().unwrap(), [CountError::WakerPanicked]);
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+}
+
+#[test]
+fn test_waker_panic_default_policy() {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    struct PanicWaker;
+
+    impl Wake for PanicWaker {
+        fn wake(self: Arc<Self>) {
+            panic!("waker");
+        }
+    }
+
+    let wg = WaitGroup::new();
+    let waker = Waker::from(Arc::new(PanicWaker));
+    let mut ctx = Context::from_waker(&waker);
+    let guard = wg.add_guard();
+    let mut f = Box::pin(wg.wait());
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    // Only logged, the guard drop does not panic
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(guard)));
+    assert!(r.is_ok());
+    assert_eq!(wg.left(), 0);
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+}
+
+#[test]
+fn test_band_waker_panic() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Wake, Waker};
+
+    struct PanicWaker;
+
+    impl Wake for PanicWaker {
+        fn wake(self: Arc<Self>) {
+            panic!("waker");
+        }
+    }
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    wg.add(2);
+    let panicking = Waker::from(Arc::new(PanicWaker));
+    let counted = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(counted.clone());
+    let mut f1 = Box::pin(wg.wait_changed());
+    let mut f2 = Box::pin(wg.wait_changed());
+    assert!(f1
+        .as_mut()
+        .poll(&mut Context::from_waker(&panicking))
+        .is_pending());
+    assert!(f2
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    // Not unwinding into done(), nor skipping the other waiter
+    wg.done();
+    assert_eq!(*errors.lock().unwrap(), [CountError::WakerPanicked]);
+    assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        f2.as_mut().poll(&mut Context::from_waker(&waker)),
+        std::task::Poll::Ready(1)
+    );
+}
+
+#[test]
+fn test_drain_waker_panic() {
+    use atomic_waitgroup::{CountError, DrainState, ErrorPolicy};
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct PanicWaker;
+
+    impl Wake for PanicWaker {
+        fn wake(self: Arc<Self>) {
+            panic!("waker");
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    let waker = Waker::from(Arc::new(PanicWaker));
+    let mut ctx = Context::from_waker(&waker);
+    // Queued behind the limit
+    let guard = wg.add_guard();
+    let mut acquire = Box::pin(wg.acquire(1));
+    assert!(acquire.as_mut().poll(&mut ctx).is_pending());
+    let mut watch = wg.watch_state();
+    {
+        let mut changed = Box::pin(watch.changed());
+        assert!(changed.as_mut().poll(&mut ctx).is_pending());
+        // Not unwinding into the guard drop, the acquirer and the zero count are notified
+        drop(guard);
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [CountError::WakerPanicked, CountError::WakerPanicked]
+        );
+        let Poll::Ready(Some(_guard)) = acquire.as_mut().poll(&mut ctx) else {
+            panic!("not admitted");
+        };
+        assert!(changed.as_mut().poll(&mut ctx).is_pending());
+        // Not unwinding into close()
+        wg.close();
+        assert_eq!(errors.lock().unwrap().len(), 3);
+        assert_eq!(
+            changed.as_mut().poll(&mut ctx),
+            Poll::Ready(Some(DrainState::Closed))
+        );
+    }
+}
+
+#[test]
+fn test_go_semantics() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::task::Context;
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .go_semantics(true)
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    let waker = futures::task::noop_waker();
+    let mut ctx = Context::from_waker(&waker);
+    wg.add(1);
+    let mut f = Box::pin(wg.wait());
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    // Positive counter during the wait is allowed
+    wg.add(1);
+    wg.done_many(2);
+    assert!(errors.lock().unwrap().is_empty());
+    // Reused before the wait returned
+    wg.add(1);
+    assert_eq!(
+        *errors.lock().unwrap(),
+        [CountError::AddDuringWait { count: 1 }]
+    );
+    wg.done();
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+    drop(f);
+    wg.add(1);
+    wg.done();
+    assert_eq!(errors.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_waiter_panic() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async {
+        let guard = wg.add_guard();
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            let f = _wg.wait();
+            tokio::pin!(f);
+            // Registered, then the task panics with the future alive
+            assert!(tokio::time::timeout(Duration::from_millis(5), &mut f)
+                .await
+                .is_err());
+            panic!("waiter");
+        });
+        assert!(th.await.unwrap_err().is_panic());
+        drop(guard);
+        wg.wait().await;
+        wg.add(1);
+        let _wg = wg.clone();
+        tokio::spawn(async move { _wg.done() });
+        wg.wait().await;
+    });
+}
+
+// Chaos duplicates and delays the wake
+#[cfg(not(feature = "chaos"))]
+#[test]
+fn test_rewake_moved() {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let first = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let second = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let wg = WaitGroup::new();
+    wg.add(2);
+    let mut f = Box::pin(wg.wait());
+    let waker = Waker::from(first.clone());
+    assert!(f
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    // Moved to another task
+    let waker = Waker::from(second.clone());
+    assert!(f
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    wg.done();
+    wg.done();
+    assert_eq!(first.0.load(Ordering::SeqCst), 0);
+    assert_eq!(second.0.load(Ordering::SeqCst), 1);
+    assert!(f.as_mut().poll(&mut Context::from_waker(&waker)).is_ready());
+}
+
+#[test]
+fn test_busy_poll() {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    let wg = WaitGroup::new();
+    let guard = wg.add_guard();
+    assert!(wg.poll_ready(0).is_pending());
+    assert!(wg.poll_ready(1).is_ready());
+    let th = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(guard);
+    });
+    let mut ctx = Context::from_waker(Waker::noop());
+    let mut f = Box::pin(wg.wait());
+    let mut polls = 0;
+    while f.as_mut().poll(&mut ctx) == Poll::Pending {
+        polls += 1;
+        std::thread::yield_now();
+    }
+    assert!(polls > 0);
+    assert!(wg.poll_ready(0).is_ready());
+    th.join().unwrap();
+}
+
+#[test]
+fn test_is_waiting() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        assert_eq!(wg.is_waiting(), None);
+        wg.add(2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            assert!(_wg.wait_to(1).await.blocked);
+        });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(wg.is_waiting(), Some(1));
+        wg.done();
+        th.await.unwrap();
+        assert_eq!(wg.is_waiting(), None);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            _wg.wait_to_with_progress(0, |_| {}).await;
+        });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(wg.is_waiting(), Some(usize::MAX));
+        wg.done();
+        th.await.unwrap();
+        assert_eq!(wg.is_waiting(), None);
+    });
+}
+
+#[test]
+fn test_notify_waiters() {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let wg = WaitGroup::new();
+    // No waiter
+    wg.notify_waiters();
+    let counter = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(counter.clone());
+    let mut ctx = Context::from_waker(&waker);
+    wg.add(1);
+    let mut f = Box::pin(wg.wait());
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    wg.notify_waiters();
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    // Still not satisfied
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    assert_eq!(wg.left(), 1);
+    let _wg = wg.clone();
+    let th = std::thread::spawn(move || _wg.wait_blocking_timeout(Duration::from_millis(100)));
+    std::thread::sleep(Duration::from_millis(10));
+    wg.notify_waiters();
+    assert!(th.join().unwrap().is_err());
+    wg.done();
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+}
+
+#[test]
+fn test_pipeline() {
+    use atomic_waitgroup::Pipeline;
+
+    let pipeline = Pipeline::new(3);
+    assert_eq!(pipeline.stage_count(), 3);
+    make_runtime(2).block_on(async move {
+        let guards: Vec<_> = (0..10).map(|_| pipeline.enter()).collect();
+        assert_eq!(pipeline.left(), [10, 0, 0]);
+        let _pipeline = pipeline.clone();
+        let th = tokio::spawn(async move {
+            for guard in guards {
+                sleep(Duration::from_millis(2)).await;
+                let guard = _pipeline.advance(guard).unwrap();
+                assert_eq!(guard.stage(), 1);
+                let guard = _pipeline.advance(guard).unwrap();
+                assert_eq!(_pipeline.left_all(), _pipeline.left().iter().sum::<usize>());
+                assert!(_pipeline.advance(guard).is_none());
+            }
+        });
+        pipeline.wait_stage(0).await;
+        assert_eq!(pipeline.stage(0).left(), 0);
+        pipeline.wait_all().await;
+        assert_eq!(pipeline.left(), [0, 0, 0]);
+        th.await.unwrap();
+        assert_eq!(pipeline.stage(2).completed(), 10);
+    });
+}
+
+#[test]
+fn test_wait_to_eq() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        wg.add(1);
+        assert!(!wg.wait_to_eq(1).await);
+        wg.add(2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            assert!(_wg.wait_to_eq(1).await);
+            assert_eq!(_wg.left(), 1);
+        });
+        sleep(Duration::from_millis(50)).await;
+        wg.done();
+        // Passing through 1
+        wg.done_many(2);
+        sleep(Duration::from_millis(50)).await;
+        assert!(!th.is_finished());
+        // Not holding the waiter slot
+        assert_eq!(wg.is_waiting(), None);
+        wg.add(2);
+        wg.done();
+        th.await.unwrap();
+        wg.done();
+        // Reached by add()
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_to_eq(2).await });
+        sleep(Duration::from_millis(50)).await;
+        wg.add(1);
+        sleep(Duration::from_millis(50)).await;
+        assert!(!th.is_finished());
+        wg.add(1);
+        assert!(th.await.unwrap());
+        assert_eq!(wg.left(), 2);
+        wg.done_many(2);
+    });
+}
+
+#[test]
+fn test_wait_above() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        wg.add(1);
+        assert!(!wg.wait_above(1).await);
+        let mut ths = Vec::new();
+        for target in [2, 3] {
+            let _wg = wg.clone();
+            ths.push(tokio::spawn(async move {
+                assert!(_wg.wait_above(target).await);
+                assert!(_wg.left() >= target);
+            }));
+        }
+        sleep(Duration::from_millis(50)).await;
+        wg.add(1);
+        sleep(Duration::from_millis(50)).await;
+        assert!(ths[0].is_finished());
+        assert!(!ths[1].is_finished());
+        // Canceled
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), wg.wait_above(4))
+                .await
+                .is_err()
+        );
+        let _guard = wg.add_guard();
+        for th in ths {
+            th.await.unwrap();
+        }
+        wg.done_many(2);
+    });
+}
+
+#[test]
+fn test_wait_changed() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        wg.add(2);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), wg.wait_changed())
+                .await
+                .is_err()
+        );
+        // Waiting along with wait()
+        let _wg = wg.clone();
+        let waiter = tokio::spawn(async move { _wg.wait().await });
+        let changed = wg.wait_changed();
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            _wg.done();
+            sleep(Duration::from_millis(20)).await;
+            _wg.add(1);
+            sleep(Duration::from_millis(20)).await;
+            _wg.done_many(2);
+        });
+        assert_eq!(changed.await, 1);
+        assert_eq!(wg.wait_changed().await, 2);
+        assert_eq!(wg.wait_changed().await, 0);
+        th.await.unwrap();
+        waiter.await.unwrap();
+    });
+}
+
+#[test]
+fn test_wait_while() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        assert_eq!(wg.wait_while(|left| left > 0).await, 0);
+        wg.add(10);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            let left = _wg
+                .wait_while(|left| {
+                    seen.push(left);
+                    !(left % 2 == 0 && left < 8)
+                })
+                .await;
+            (left, seen)
+        });
+        for _ in 0..4 {
+            sleep(Duration::from_millis(20)).await;
+            wg.done();
+        }
+        let (left, seen) = th.await.unwrap();
+        assert_eq!(left, 6);
+        assert_eq!(seen.first(), Some(&10));
+        assert_eq!(seen.last(), Some(&6));
+        wg.done_many(6);
+    });
+}
+
+#[test]
+fn test_wait_in() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        wg.add(3);
+        assert_eq!(wg.wait_in(2..=5).await, 3);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_in(5..=6).await });
+        sleep(Duration::from_millis(20)).await;
+        // Rising into the range
+        wg.add(1);
+        sleep(Duration::from_millis(20)).await;
+        assert!(!th.is_finished());
+        wg.add(2);
+        assert_eq!(th.await.unwrap(), 6);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_in(1..=2).await });
+        sleep(Duration::from_millis(20)).await;
+        // Falling through the range, then back into it
+        wg.done_many(6);
+        sleep(Duration::from_millis(20)).await;
+        assert!(!th.is_finished());
+        wg.add(1);
+        assert_eq!(th.await.unwrap(), 1);
+        wg.done();
+    });
+}
+
+#[test]
+#[should_panic]
+#[allow(clippy::reversed_empty_ranges)]
+fn test_wait_in_empty() {
+    let wg = WaitGroup::new();
+    drop(wg.wait_in(3..=2));
+}
+
+#[test]
+fn test_wait_any_of() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        wg.add(5);
+        assert_eq!(wg.wait_any_of(&[1, 5]).await, 1);
+        let level = wg.wait_any_of(&[8, 1, 3]);
+        wg.add(1);
+        sleep(Duration::from_millis(20)).await;
+        // Crossing both lower thresholds at once
+        wg.done_many(6);
+        assert_eq!(level.await, 2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_any_of(&[8, 2]).await });
+        sleep(Duration::from_millis(20)).await;
+        wg.add(8);
+        // 2 is crossed on the way to 8
+        assert_eq!(th.await.unwrap(), 1);
+        wg.done_many(8);
+    });
+}
+
+#[test]
+fn test_wait_outcome() {
+    use atomic_waitgroup::WaitOutcome;
+
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        let outcome: WaitOutcome = wg.wait().await;
+        assert!(!outcome.blocked);
+        assert_eq!(outcome.elapsed, Duration::ZERO);
+        wg.add(2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_to(1).await });
+        sleep(Duration::from_millis(50)).await;
+        wg.done();
+        let outcome = th.await.unwrap();
+        assert!(outcome.blocked);
+        assert_eq!(outcome.polls, 2);
+        assert!(outcome.elapsed >= Duration::from_millis(40));
+        wg.done();
+    });
+}
+
+#[test]
+fn test_wait_outcome_left() {
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        wg.add(2);
+        assert_eq!(wg.wait_to(3).await.left, 2);
+        wg.add(2);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move { _wg.wait_to(2).await });
+        sleep(Duration::from_millis(50)).await;
+        // Racing done() beyond the target
+        wg.done();
+        wg.done_many(2);
+        let outcome = th.await.unwrap();
+        assert!(outcome.blocked);
+        assert_eq!(outcome.left, 1);
+        wg.done();
+    });
+}
+
+#[test]
+fn test_generation() {
+    let wg = WaitGroup::new();
+    assert_eq!(wg.generation(), 0);
+    make_runtime(2).block_on(async move {
+        let late = wg.add_generation_guard().unwrap();
+        assert_eq!(late.generation(), 0);
+        let _wg = wg.clone();
+        let waiter = tokio::spawn(async move { _wg.wait_generation(0).await });
+        sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        // Abandoning the cycle
+        assert_eq!(wg.next_generation(), 1);
+        waiter.await.unwrap();
+        assert_eq!(wg.left(), 0);
+        let guards: Vec<_> = (0..3).map(|_| wg.add_generation_guard().unwrap()).collect();
+        assert!(guards.iter().all(|guard| guard.generation() == 1));
+        // The late drop does not count against generation 1
+        drop(late);
+        assert_eq!(wg.left(), 3);
+        let th = tokio::spawn(async move {
+            for guard in guards {
+                sleep(Duration::from_millis(5)).await;
+                drop(guard);
+            }
+        });
+        wg.wait_generation(1).await;
+        assert_eq!(wg.left(), 0);
+        wg.wait_generation(0).await;
+        th.await.unwrap();
+    });
+}
+
+#[test]
+fn test_generation_closed() {
+    let wg = WaitGroup::new();
+    let guard = wg.add_generation_guard().unwrap();
+    wg.close();
+    // Rejected, no unit to give back on drop
+    assert!(wg.add_generation_guard().is_none());
+    assert_eq!(wg.left(), 1);
+    drop(guard);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_generation_waker_panic() {
+    use atomic_waitgroup::{CountError, ErrorPolicy};
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Wake, Waker};
+
+    struct PanicWaker;
+
+    impl Wake for PanicWaker {
+        fn wake(self: Arc<Self>) {
+            panic!("waker");
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let _errors = errors.clone();
+    let wg = WaitGroup::builder()
+        .error_policy(ErrorPolicy::Callback(Arc::new(move |e: &CountError| {
+            _errors.lock().unwrap().push(*e)
+        })))
+        .build();
+    let waker = Waker::from(Arc::new(PanicWaker));
+    let mut ctx = Context::from_waker(&waker);
+    // Not unwinding into the guard drop
+    let guard = wg.add_generation_guard().unwrap();
+    let mut f = Box::pin(wg.wait_generation(0));
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    drop(guard);
+    assert_eq!(*errors.lock().unwrap(), [CountError::WakerPanicked]);
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+    // Nor into next_generation()
+    let _guard = wg.add_generation_guard().unwrap();
+    let mut f = Box::pin(wg.wait_generation(0));
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    assert_eq!(wg.next_generation(), 1);
+    assert_eq!(errors.lock().unwrap().len(), 2);
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+}
+
+#[test]
+fn test_generation_reentrant() {
+    use atomic_waitgroup::WaitGroupObserver;
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    #[derive(Default)]
+    struct Generation {
+        wg: OnceLock<WaitGroup>,
+        seen: Mutex<Vec<u64>>,
+    }
+
+    impl WaitGroupObserver for Generation {
+        fn on_done(&self, _count: usize, _left: usize) {
+            // Not deadlocking on the lock of the generation guard
+            let wg = self.wg.get().unwrap();
+            self.seen.lock().unwrap().push(wg.generation());
+        }
+    }
+
+    let observer = Arc::new(Generation::default());
+    let wg = WaitGroup::builder().observer(observer.clone()).build();
+    let _ = observer.wg.set(wg.clone());
+    let guard = wg.add_generation_guard().unwrap();
+    drop(guard);
+    let _abandoned = wg.add_generation_guard().unwrap();
+    assert_eq!(wg.next_generation(), 1);
+    assert_eq!(*observer.seen.lock().unwrap(), [0, 1]);
+    assert_eq!(wg.left(), 0);
+}
+
+#[test]
+fn test_reset() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        let guard = wg.add_generation_guard().unwrap();
+        wg.add(3);
+        let _wg = wg.clone();
+        let waiter = tokio::spawn(async move { _wg.wait().await });
+        let _wg = wg.clone();
+        let band = tokio::spawn(async move { _wg.wait_in(0..=1).await });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(wg.is_waiting(), Some(0));
+        wg.reset();
+        let outcome = waiter.await.unwrap();
+        assert!(outcome.blocked);
+        assert_eq!(outcome.left, 0);
+        assert_eq!(band.await.unwrap(), 0);
+        assert_eq!(wg.is_waiting(), None);
+        assert_eq!(wg.generation(), 1);
+        // Reused
+        wg.add(1);
+        drop(guard);
+        assert_eq!(wg.left(), 1);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            _wg.done();
+        });
+        assert!(wg.wait().await.blocked);
+        th.await.unwrap();
+    });
+}
+
+// Chaos duplicates and delays the wake
+#[cfg(not(feature = "chaos"))]
+#[test]
+fn test_reset_repoll() {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let woken = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(woken.clone());
+    let mut ctx = Context::from_waker(&waker);
+    let wg = WaitGroup::new();
+    wg.add(1);
+    let mut f = Box::pin(wg.wait());
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    wg.reset();
+    assert_eq!(woken.0.load(Ordering::SeqCst), 1);
+    // Reused before the woken future is polled, it registers again
+    wg.add(1);
+    assert!(f.as_mut().poll(&mut ctx).is_pending());
+    assert_eq!(wg.is_waiting(), Some(0));
+    wg.done();
+    assert_eq!(woken.0.load(Ordering::SeqCst), 2);
+    assert!(f.as_mut().poll(&mut ctx).is_ready());
+    assert_eq!(wg.is_waiting(), None);
+}
+
+#[test]
+fn test_drained() {
+    use atomic_waitgroup::DrainState;
+
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        // Zero but open
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), wg.drained())
+                .await
+                .is_err()
+        );
+        let guards: Vec<_> = (0..3).map(|_| wg.add_guard()).collect();
+        let drained = tokio::spawn(wg.drained());
+        wg.close();
+        assert!(wg.try_add_guard().is_none());
+        sleep(Duration::from_millis(20)).await;
+        assert!(!drained.is_finished());
+        drop(guards);
+        drained.await.unwrap();
+        assert_eq!(wg.state(), DrainState::Drained);
+        // Closed at zero
+        let wg = WaitGroup::new();
+        wg.close();
+        wg.drained().await;
+    });
+}
+
+#[test]
+fn test_notify_wait_while() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let wg = WaitGroup::new();
+    let paused = Arc::new(AtomicBool::new(true));
+    make_runtime(2).block_on(async move {
+        wg.add(2);
+        let (_wg, _paused) = (wg.clone(), paused.clone());
+        let th = tokio::spawn(async move {
+            _wg.wait_while(|left| left > 1 && _paused.load(Ordering::SeqCst))
+                .await
+        });
+        let _wg = wg.clone();
+        let above = tokio::spawn(async move { _wg.wait_above(3).await });
+        sleep(Duration::from_millis(20)).await;
+        wg.notify_waiters();
+        sleep(Duration::from_millis(20)).await;
+        assert!(!th.is_finished());
+        paused.store(false, Ordering::SeqCst);
+        wg.notify_waiters();
+        assert_eq!(th.await.unwrap(), 2);
+        // Still false for wait_above()
+        sleep(Duration::from_millis(20)).await;
+        assert!(!above.is_finished());
+        wg.add(1);
+        assert!(above.await.unwrap());
+        wg.done_many(3);
+    });
+}
+
+#[test]
+fn test_waiter_enable() {
+    let wg = WaitGroup::new();
+    make_runtime(2).block_on(async move {
+        let mut waiter = wg.waiter();
+        waiter.enable();
+        assert_eq!(wg.is_waiting(), None);
+        assert!(!waiter.await.blocked);
+        wg.add(2);
+        let mut waiter = wg.waiter_to(1);
+        waiter.enable();
+        assert_eq!(wg.is_waiting(), Some(1));
+        // Dropped, the registration is canceled
+        drop(waiter);
+        assert_eq!(wg.is_waiting(), None);
+        let mut waiter = wg.waiter();
+        waiter.enable();
+        // A burst of done() before the first poll
+        let _wg = wg.clone();
+        std::thread::spawn(move || _wg.done_many(2)).join().unwrap();
+        let outcome = waiter.await;
+        assert!(outcome.blocked);
+        assert_eq!(outcome.polls, 1);
+        assert_eq!(outcome.left, 0);
+        assert_eq!(wg.is_waiting(), None);
+        // Reached at enable(), an add() before the first poll makes it wait
+        let mut waiter = wg.waiter();
+        waiter.enable();
+        assert_eq!(wg.is_waiting(), None);
+        wg.add(1);
+        let _wg = wg.clone();
+        let th = tokio::spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            _wg.done();
+        });
+        let outcome = waiter.await;
+        assert!(outcome.blocked);
+        assert!(outcome.elapsed > Duration::ZERO);
+        th.await.unwrap();
+    });
+}
+
+#[test]
+fn test_waiter_unpin() {
+    use atomic_waitgroup::{Storage, Waiter};
+
+    struct Drain<'a, S: Storage> {
+        waiter: Waiter<'a, S>,
+    }
+
+    let wg = WaitGroup::new();
+    make_runtime(1).block_on(async move {
+        let guard = wg.add_guard();
+        let mut drain = Drain { waiter: wg.wait() };
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            drop(guard);
+        });
+        // Polled by reference, without pinning
+        assert!((&mut drain.waiter).await.blocked);
+    });
+}